@@ -0,0 +1,38 @@
+#![feature(test)]
+
+extern crate test;
+
+use morningstar::*;
+use test::{black_box, Bencher};
+
+const KEY: u64 = 0x0123456789abcdef;
+const PLAINTEXT: u64 = 0x4e6f772069732074;
+
+#[bench]
+fn reference_weak_encrypt(b: &mut Bencher) {
+    let keys = generate_keys::<16>(Bits::new(KEY));
+    b.iter(|| weak_encrypt(black_box(Bits::new(PLAINTEXT)), &keys));
+}
+
+#[bench]
+fn sp_weak_encrypt(b: &mut Bencher) {
+    let keys = generate_keys::<16>(Bits::new(KEY));
+    b.iter(|| sp::weak_encrypt(black_box(Bits::new(PLAINTEXT)), &keys));
+}
+
+#[bench]
+fn reference_encrypt(b: &mut Bencher) {
+    b.iter(|| encrypt(black_box(Bits::new(PLAINTEXT)), Bits::new(KEY)));
+}
+
+#[bench]
+fn sp_encrypt(b: &mut Bencher) {
+    b.iter(|| sp::encrypt(black_box(Bits::new(PLAINTEXT)), Bits::new(KEY)));
+}
+
+#[bench]
+fn bitsliced_weak_encrypt_256(b: &mut Bencher) {
+    let keys = generate_keys::<16>(Bits::new(KEY));
+    let blocks: Vec<Bits<64>> = (0..256).map(|i| Bits::new(PLAINTEXT ^ i)).collect();
+    b.iter(|| bitslice::weak_encrypt_many(black_box(&blocks), &keys));
+}
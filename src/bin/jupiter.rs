@@ -1,84 +1,464 @@
+use morningstar::cipher::{BlockCipher, ReducedDes, ReducedRounds};
+use morningstar::ddt::Ddt;
+use morningstar::differential::{self, DifferentialAttack};
+use morningstar::lat::Lat;
+use morningstar::linear::{self, LinearAttack};
 use morningstar::*;
 use rand::random;
 use rayon::prelude::*;
+use std::process::exit;
+
+const USAGE: &str = "\
+usage: jupiter <command> [options]
+
+commands:
+  linear               estimate the bias of a linear approximation
+  differential         estimate the probability of a differential
+  linear-attack        recover last round key bits with Matsui's Algorithm 2
+  differential-attack  recover last round key bits with chosen plaintext pairs
+  encrypt <block>...   encrypt hex blocks
+  decrypt <block>...   decrypt hex blocks
+  ddt                  print the difference distribution table of an S-box
+  lat                  print the linear approximation table of an S-box
+
+options:
+  --rounds <n>      number of rounds (default 3 for the estimates, 8 for the attacks
+                    and 16 for encrypt and decrypt)
+  --input <hex>     input mask or difference (default: the best one the search finds,
+                    or an iterated 2 round characteristic beyond 5 rounds)
+  --output <hex>    output mask or difference (default like --input)
+  --samples <n>     plaintexts or pairs to encrypt (default 10000000 for the estimates
+                    and what the trail or characteristic needs for the attacks)
+  --key <hex>       the key, or `random` (default random, required for encrypt and
+                    decrypt)
+  --sbox <n>        S-box for ddt and lat, 1 to 8
+  --no-ip           encrypt and decrypt without the initial and final permutations
+  --threads <n>     number of worker threads (default: one per core)
+  --format <fmt>    `text` or `csv` (default text)
+  --help            print this message
+
+Masks and differences of the estimates live in the domain of `weak_encrypt`, without IP.
+differential-attack exits with status 1 if no pair survives its filter.";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Text,
+    Csv,
+}
+
+#[derive(Debug)]
+struct Options {
+    command: String,
+    rounds: Option<usize>,
+    input: Option<Bits<64>>,
+    output: Option<Bits<64>>,
+    samples: Option<usize>,
+    key: Option<Bits<64>>,
+    sbox: Option<usize>,
+    ip: bool,
+    threads: Option<usize>,
+    format: Format,
+    blocks: Vec<Bits<64>>,
+}
 
 fn main() {
-    // Differential
-    // let d_in = Bits::new(0x00_80_82_00_60_00_00_00);
-    // let d_out = Bits::new(0x60_00_00_00_00_00_00_00);
-    // let rounds = 2;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return;
+    }
+
+    if let Err(error) = parse(&args).and_then(|options| run(&options)) {
+        eprintln!("error: {error}\n\n{USAGE}");
+        exit(2);
+    }
+}
+
+fn parse_hex(value: &str) -> Result<u64, String> {
+    let digits = value.trim_start_matches("0x").replace('_', "");
+    u64::from_str_radix(&digits, 16).map_err(|_| format!("`{value}` is not a 64 bit hex number"))
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let mut options = Options {
+        command: args.next().unwrap().clone(),
+        rounds: None,
+        input: None,
+        output: None,
+        samples: None,
+        key: None,
+        sbox: None,
+        ip: true,
+        threads: None,
+        format: Format::Text,
+        blocks: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "--no-ip" {
+            options.ip = false;
+            continue;
+        }
 
-    // let d_in = Bits::new(0x00_80_82_00_60_00_00_00);
-    // let d_out = Bits::new(0x00_80_82_00_60_00_00_00);
-    // let rounds = 3;
+        if !arg.starts_with("--") {
+            if options.command != "encrypt" && options.command != "decrypt" {
+                return Err(format!("unexpected argument `{arg}`"));
+            }
+            options.blocks.push(Bits::new(parse_hex(arg)?));
+            continue;
+        }
 
-    // let d_in = Bits::new(0x40_5C_00_00_04_00_00_00);
-    // let d_out = Bits::new(0x40_5C_00_00_04_00_00_00);
-    // let rounds = 5;
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{arg}` needs a value"))?;
 
-    // estimate_diff_probability(d_in, d_out, rounds);
+        match arg.as_str() {
+            "--rounds" => options.rounds = Some(parse_number(value)?),
+            "--input" => options.input = Some(Bits::new(parse_hex(value)?)),
+            "--output" => options.output = Some(Bits::new(parse_hex(value)?)),
+            "--samples" => options.samples = Some(parse_number(value)?),
+            "--key" if value == "random" => options.key = None,
+            "--key" => options.key = Some(Bits::new(parse_hex(value)?)),
+            "--sbox" => options.sbox = Some(parse_number(value)?),
+            "--threads" => options.threads = Some(parse_number(value)?),
+            "--format" => {
+                options.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "csv" => Format::Csv,
+                    _ => return Err(format!("unknown format `{value}`")),
+                }
+            }
+            _ => return Err(format!("unknown option `{arg}`")),
+        }
+    }
 
-    // Linear
-    // let alpha = Bits::new(0x21_04_00_80__00_00_80_00);
-    // let beta = alpha;
-    // estimate_linear_probability(alpha, beta, 3);
+    if options.samples == Some(0) {
+        return Err("`--samples` has to be at least 1".to_string());
+    }
 
-    let alpha = Bits::new(0x01_04_00_80__00_01_10_00);
-    let beta = Bits::new(0x21_04_00_80__00_00_80_00);
-    estimate_linear_probability::<7>(alpha, beta);
+    if let Some(rounds) = options.rounds {
+        if !(1..=16).contains(&rounds) {
+            return Err("DES has 1 to 16 rounds".to_string());
+        }
+    }
+
+    Ok(options)
 }
 
-fn estimate_linear_probability<const R: usize>(alpha: Bits<64>, beta: Bits<64>) {
-    let total = 10_000_000;
+fn run(options: &Options) -> Result<(), String> {
+    if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| error.to_string())?;
+    }
 
-    // let key = Bits::new(0xdeadbeefcafebabe);
-    let key = Bits::new(random::<u64>());
-    let keys = generate_keys(key);
+    match options.command.as_str() {
+        "linear" => linear_estimate(options),
+        "differential" => differential_estimate(options),
+        "linear-attack" => linear_attack(options),
+        "differential-attack" => differential_attack(options),
+        "encrypt" => crypt(options, encrypt_rounds),
+        "decrypt" => crypt(options, decrypt_rounds),
+        "ddt" => {
+            let ddt = Ddt::des(sbox_number(options)?);
+            match options.format {
+                Format::Text => print!("{ddt}"),
+                Format::Csv => print!("{}", ddt.to_csv()),
+            }
+            Ok(())
+        }
+        "lat" => {
+            let lat = Lat::des(sbox_number(options)?);
+            match options.format {
+                Format::Text => print!("{lat}"),
+                Format::Csv => print!("{}", lat.to_csv()),
+            }
+            Ok(())
+        }
+        command => Err(format!("unknown command `{command}`")),
+    }
+}
 
-    let ones: u64 = (0..total)
-        .into_par_iter()
-        .map(|_| {
-            let plaintext = Bits::new(random::<u64>());
+fn sbox_number(options: &Options) -> Result<usize, String> {
+    match options.sbox {
+        Some(sbox) if (1..=8).contains(&sbox) => Ok(sbox),
+        Some(_) => Err("there are 8 S-boxes".to_string()),
+        None => Err("`--sbox` is required".to_string()),
+    }
+}
 
-            let ciphertext = weak_encrypt::<R>(plaintext, &keys);
+/// The key from the options, or a random one that gets printed so the run can be
+/// repeated.
+fn key(options: &Options) -> Bits<64> {
+    options.key.unwrap_or_else(|| {
+        let key = Bits::new(random());
+        eprintln!("key: {:016x}", key.as_u64());
+        key
+    })
+}
 
-            // println!("{:x}", cipher_diff.as_u64());
-            (alpha.dot_product(plaintext) ^ beta.dot_product(ciphertext)) as u64
-        })
-        .sum();
+/// The masks or differences from the options, falling back to `best` for the ones
+/// that weren't given.
+fn input_output(
+    options: &Options,
+    best: impl FnOnce() -> (Bits<64>, Bits<64>),
+) -> (Bits<64>, Bits<64>) {
+    match (options.input, options.output) {
+        (Some(input), Some(output)) => (input, output),
+        (input, output) => {
+            let (best_input, best_output) = best();
+            (input.unwrap_or(best_input), output.unwrap_or(best_output))
+        }
+    }
+}
 
-    let probability = ones as f64 / total as f64;
+/// Space separated, so it can go into a CSV field
+fn list(values: &[usize]) -> String {
+    let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+    values.join(" ")
+}
+
+fn print_results(format: Format, results: &[(&str, String)]) {
+    match format {
+        Format::Text => {
+            for (name, value) in results {
+                println!("{name}: {value}");
+            }
+        }
+        Format::Csv => {
+            let names: Vec<&str> = results.iter().map(|&(name, _)| name).collect();
+            let values: Vec<&str> = results.iter().map(|(_, value)| value.as_str()).collect();
+            println!("{}", names.join(","));
+            println!("{}", values.join(","));
+        }
+    }
+}
 
-    println!("ones:  {}", 100.0 * probability);
-    println!("zeros: {}", 100.0 * (1.0 - probability));
+fn linear_estimate(options: &Options) -> Result<(), String> {
+    let rounds = options.rounds.unwrap_or(3);
+    let (alpha, beta) = input_output(options, || {
+        let best = &trail::search(rounds, 1)[0];
+        (best.input, best.output)
+    });
 
-    println!("bias: {}", (probability - 0.5).abs());
-    println!("correlation: {}", (2.0 * probability - 1.0).abs());
+    let cipher = ReducedDes::new((key(options), rounds));
+    let total = options.samples.unwrap_or(10_000_000);
+    let probability = estimate_linear_probability(&cipher, alpha, beta, total);
+
+    print_results(
+        options.format,
+        &[
+            ("rounds", rounds.to_string()),
+            ("input", format!("{:016x}", alpha.as_u64())),
+            ("output", format!("{:016x}", beta.as_u64())),
+            ("samples", total.to_string()),
+            ("ones", probability.to_string()),
+            ("bias", (probability - 0.5).abs().to_string()),
+            ("correlation", (2.0 * probability - 1.0).abs().to_string()),
+        ],
+    );
+    Ok(())
 }
 
-fn estimate_diff_probability<const R: usize>(d_in: Bits<64>, d_out: Bits<64>) {
-    let total = 10_000_000;
+fn differential_estimate(options: &Options) -> Result<(), String> {
+    let rounds = options.rounds.unwrap_or(3);
+    if rounds < 2 && (options.input.is_none() || options.output.is_none()) {
+        return Err("every one round characteristic has probability 1".to_string());
+    }
+    let (d_in, d_out) = input_output(options, || {
+        let best = &differential::characteristics(rounds, 1)[0];
+        (best.input, best.output)
+    });
 
-    let key = Bits::new(0xdeadbeefcafebabe);
-    let keys = generate_keys(key);
+    let cipher = ReducedDes::new((key(options), rounds));
+    let total = options.samples.unwrap_or(10_000_000);
+    let matches = estimate_diff_probability(&cipher, d_in, d_out, total);
 
-    let matches: u64 = (0..total)
-        .into_par_iter()
-        .map(|_| {
-            let plaintext_a = Bits::new(random::<u64>());
-            let plaintext_b = plaintext_a ^ d_in;
+    print_results(
+        options.format,
+        &[
+            ("rounds", rounds.to_string()),
+            ("input", format!("{:016x}", d_in.as_u64())),
+            ("output", format!("{:016x}", d_out.as_u64())),
+            ("pairs", total.to_string()),
+            ("matches", matches.to_string()),
+            ("probability", (matches as f64 / total as f64).to_string()),
+        ],
+    );
+    Ok(())
+}
+
+fn linear_attack(options: &Options) -> Result<(), String> {
+    let rounds = options.rounds.unwrap_or(8);
+    if rounds < 2 {
+        return Err("the attack needs at least 2 rounds".to_string());
+    }
+    let attack = LinearAttack::for_rounds(rounds)
+        .ok_or("no trail activates few enough S-boxes in the last round")?;
+
+    let cipher = ReducedDes::new((key(options), rounds));
+    let total = options
+        .samples
+        .unwrap_or(attack.trail().plaintexts_needed() as usize);
+    let pairs = linear::known_plaintexts(&cipher, total);
+    let candidates = attack.run(&pairs);
+
+    let subkey = attack.subkey(cipher.last_round_key());
+    let rank = linear::rank(&candidates, subkey).unwrap();
+
+    print_results(
+        options.format,
+        &[
+            ("rounds", rounds.to_string()),
+            ("sboxes", list(&attack.active_sboxes())),
+            ("plaintexts", total.to_string()),
+            ("best", format!("{:x}", candidates[0].key)),
+            ("deviation", candidates[0].deviation.to_string()),
+            ("correct", format!("{subkey:x}")),
+            ("rank", rank.to_string()),
+        ],
+    );
+    Ok(())
+}
+
+fn differential_attack(options: &Options) -> Result<(), String> {
+    let rounds = options.rounds.unwrap_or(8);
+    if rounds < 3 {
+        return Err("the attack needs at least 3 rounds".to_string());
+    }
+    let attack = DifferentialAttack::for_rounds(rounds)
+        .ok_or("no characteristic activates an S-box in the last round")?;
+
+    let cipher = ReducedDes::new((key(options), rounds));
+    let total = options.samples.unwrap_or(attack.pairs_needed());
+    let recovery = attack.run(&cipher, total);
+
+    // Without a single surviving pair every key guess is as good as any other
+    if recovery.surviving_pairs == 0 {
+        eprintln!("error: none of the {total} pairs survived the filter, try more `--samples`");
+        exit(1);
+    }
+
+    let round_key = cipher.last_round_key();
+    let (best, mask) = recovery.best_key();
+
+    print_results(
+        options.format,
+        &[
+            ("rounds", rounds.to_string()),
+            ("sboxes", list(&attack.active_sboxes())),
+            ("pairs", recovery.pairs.to_string()),
+            ("surviving_pairs", recovery.surviving_pairs.to_string()),
+            ("best", format!("{:012x}", best.as_u64())),
+            (
+                "correct",
+                format!("{:012x}", round_key.as_u64() & mask.as_u64()),
+            ),
+            ("ranks", list(&recovery.ranks(round_key))),
+        ],
+    );
+    Ok(())
+}
 
-            let ciphertext_a = weak_encrypt::<R>(plaintext_a, &keys);
-            let ciphertext_b = weak_encrypt::<R>(plaintext_b, &keys);
+fn crypt(
+    options: &Options,
+    f: fn(Bits<64>, &[Bits<48>], std::ops::RangeInclusive<usize>, RoundOptions) -> Bits<64>,
+) -> Result<(), String> {
+    let key = options.key.ok_or("`--key` is required")?;
+    let rounds = options.rounds.unwrap_or(16);
+    let keys = generate_keys::<16>(key);
 
-            let cipher_diff = ciphertext_a ^ ciphertext_b;
+    let round_options = RoundOptions {
+        initial_permutation: options.ip,
+        ..RoundOptions::default()
+    };
 
-            // println!("{:x}", cipher_diff.as_u64());
-            (cipher_diff.as_u64() == d_out.as_u64()) as u64
+    for &block in &options.blocks {
+        let result = f(block, &keys, 1..=rounds, round_options);
+        match options.format {
+            Format::Text => println!("{:016x}", result.as_u64()),
+            Format::Csv => println!("{:016x},{:016x}", block.as_u64(), result.as_u64()),
+        }
+    }
+    Ok(())
+}
+
+fn random_block<const N: usize>() -> Bits<N> {
+    Bits::new(random::<u64>() >> (64 - N))
+}
+
+/// Blocks per batch handed to `BlockCipher::encrypt_many`, so ciphers that encrypt many
+/// blocks at once (like the bitsliced reduced-round DES) get enough of them
+const BATCH: usize = 1 << 12;
+
+/// Runs `f` on `total` random plaintexts in parallel batches and sums up the results
+fn batches<const N: usize>(total: usize, f: impl Fn(Vec<Bits<N>>) -> u64 + Sync) -> u64 {
+    (0..total.div_ceil(BATCH))
+        .into_par_iter()
+        .map(|batch| {
+            let count = BATCH.min(total - batch * BATCH);
+            f((0..count).map(|_| random_block()).collect())
         })
-        .sum();
+        .sum()
+}
+
+/// Fraction of `total` random plaintexts for which alpha·P ^ beta·C is 1
+fn estimate_linear_probability<C, const N: usize>(
+    cipher: &C,
+    alpha: Bits<N>,
+    beta: Bits<N>,
+    total: usize,
+) -> f64
+where
+    C: BlockCipher<N> + Sync,
+{
+    let ones = batches(total, |plaintexts| {
+        let ciphertexts = cipher.encrypt_many(&plaintexts);
+
+        plaintexts
+            .iter()
+            .zip(&ciphertexts)
+            .filter(|&(&plaintext, &ciphertext)| {
+                alpha.dot_product(plaintext) ^ beta.dot_product(ciphertext)
+            })
+            .count() as u64
+    });
+
+    ones as f64 / total as f64
+}
+
+/// Number of `total` random pairs with difference `d_in` whose ciphertexts differ by
+/// `d_out`
+fn estimate_diff_probability<C, const N: usize>(
+    cipher: &C,
+    d_in: Bits<N>,
+    d_out: Bits<N>,
+    total: usize,
+) -> u64
+where
+    C: BlockCipher<N> + Sync,
+{
+    batches(total, |plaintexts_a: Vec<Bits<N>>| {
+        let plaintexts_b: Vec<Bits<N>> = plaintexts_a.iter().map(|&a| a ^ d_in).collect();
+
+        let ciphertexts_a = cipher.encrypt_many(&plaintexts_a);
+        let ciphertexts_b = cipher.encrypt_many(&plaintexts_b);
 
-    println!("{matches:?}");
-    println!("{:?}", matches as f64 / total as f64);
-    // println!("{:?} / 64", 64.0 * matches as f64 / total as f64);
+        ciphertexts_a
+            .iter()
+            .zip(&ciphertexts_b)
+            .filter(|&(&a, &b)| a ^ b == d_out)
+            .count() as u64
+    })
 }
@@ -1,6 +1,6 @@
 use std::ops::BitXor;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Bits<const N: usize> {
     inner: u64,
 }
@@ -47,6 +47,11 @@ impl<const N: usize> Bits<N> {
         N
     }
 
+    /// Returns true if the bit array has a width of zero
+    pub fn is_empty(self) -> bool {
+        N == 0
+    }
+
     /// Split into left and right
     /// Left value is Msb
     pub fn split<const M: usize>(self) -> (Bits<{ M }>, Bits<{ N - M }>) {
@@ -163,7 +168,7 @@ mod test {
         println!("{:?}", output);
 
         // Check tht only bit 26 is set
-        assert_eq!(output.get(5), true);
+        assert!(output.get(5));
         assert_eq!(output.inner.count_ones(), 1);
 
         // Check that applying the inverse works
@@ -218,13 +223,13 @@ mod test {
     #[test]
     fn test_get() {
         let one_one: Bits<1> = Bits::new(0b1);
-        assert_eq!(one_one.get(1), true);
+        assert!(one_one.get(1));
 
         let one_one: Bits<2> = Bits::new(0b01);
-        assert_eq!(one_one.get(2), true);
+        assert!(one_one.get(2));
 
         let one_one: Bits<8> = Bits::new(0b0000_1000);
-        assert_eq!(one_one.get(5), true);
+        assert!(one_one.get(5));
     }
 
     #[test]
@@ -263,17 +268,18 @@ mod test {
         let val2: Bits<8> = Bits::new(0b0010_1011);
         let val3: Bits<8> = Bits::new(0b0010_1100);
 
-        assert_eq!(val1.dot_product(val2), false);
-        assert_eq!(val2.dot_product(val1), false);
+        assert!(!val1.dot_product(val2));
+        assert!(!val2.dot_product(val1));
 
-        assert_eq!(val1.dot_product(val3), true);
-        assert_eq!(val3.dot_product(val1), true);
+        assert!(val1.dot_product(val3));
+        assert!(val3.dot_product(val1));
 
-        assert_eq!(val2.dot_product(val3), false);
-        assert_eq!(val3.dot_product(val2), false);
+        assert!(!val2.dot_product(val3));
+        assert!(!val3.dot_product(val2));
     }
 
     #[test]
+    #[cfg(target_arch = "x86_64")]
     #[ignore = "doing this optimization later"]
     fn test_fast_permute() {
         // const P: [u8; 32] = [
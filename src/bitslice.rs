@@ -0,0 +1,375 @@
+//! Bitsliced DES. Every bit of the state gets a machine word (a "lane") holding that bit
+//! of 64 blocks, or 256 with AVX2, so every gate works on all of them at once.
+mod sboxes;
+
+use crate::{generate_keys, Bits, E, IP, IP_INVERSE, P, ROUNDS};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{
+    __m256i, _mm256_and_si256, _mm256_or_si256, _mm256_set1_epi64x, _mm256_xor_si256,
+};
+
+/// A machine word holding one bit of `BLOCKS` different blocks.
+pub trait Lane:
+    Copy + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + Not<Output = Self>
+{
+    /// How many u64 words the lane is made of
+    const WORDS: usize;
+    /// How many blocks a lane holds
+    const BLOCKS: usize = 64 * Self::WORDS;
+    const ZERO: Self;
+
+    fn word(&self, k: usize) -> u64;
+    fn set_word(&mut self, k: usize, word: u64);
+}
+
+impl Lane for u64 {
+    const WORDS: usize = 1;
+    const ZERO: Self = 0;
+
+    fn word(&self, _: usize) -> u64 {
+        *self
+    }
+
+    fn set_word(&mut self, _: usize, word: u64) {
+        *self = word;
+    }
+}
+
+/// Four u64 lanes in an AVX2 register. Its operations are AVX2 instructions, so it may
+/// only be used once `is_x86_feature_detected!("avx2")` said yes, which is why it isn't
+/// public.
+#[cfg(target_arch = "x86_64")]
+#[derive(Clone, Copy)]
+struct U64x4(__m256i);
+
+#[cfg(target_arch = "x86_64")]
+macro_rules! impl_u64x4_op {
+    ($trait:ident, $fn:ident, $intrinsic:ident) => {
+        impl $trait for U64x4 {
+            type Output = Self;
+
+            #[inline(always)]
+            fn $fn(self, rhs: Self) -> Self {
+                // Safety: U64x4 is only used after checking for AVX2
+                U64x4(unsafe { $intrinsic(self.0, rhs.0) })
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+impl_u64x4_op!(BitAnd, bitand, _mm256_and_si256);
+#[cfg(target_arch = "x86_64")]
+impl_u64x4_op!(BitOr, bitor, _mm256_or_si256);
+#[cfg(target_arch = "x86_64")]
+impl_u64x4_op!(BitXor, bitxor, _mm256_xor_si256);
+
+#[cfg(target_arch = "x86_64")]
+impl Not for U64x4 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn not(self) -> Self {
+        // Safety: U64x4 is only used after checking for AVX2
+        U64x4(unsafe { _mm256_xor_si256(self.0, _mm256_set1_epi64x(-1)) })
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Lane for U64x4 {
+    const WORDS: usize = 4;
+    // Safety: any 256 bits are a valid __m256i
+    const ZERO: Self = U64x4(unsafe { std::mem::transmute::<[u64; 4], __m256i>([0; 4]) });
+
+    fn word(&self, k: usize) -> u64 {
+        // Safety: as for ZERO, and the other way around
+        let words: [u64; 4] = unsafe { std::mem::transmute(self.0) };
+        words[k]
+    }
+
+    fn set_word(&mut self, k: usize, word: u64) {
+        // Safety: as for ZERO
+        let mut words: [u64; 4] = unsafe { std::mem::transmute(self.0) };
+        words[k] = word;
+        self.0 = unsafe { std::mem::transmute::<[u64; 4], __m256i>(words) };
+    }
+}
+
+/// Transposes a 64x64 bit matrix in place. Bit j (Lsb first) of row i ends up as
+/// bit i (Lsb first) of row j.
+pub fn transpose(m: &mut [u64; 64]) {
+    let mut j = 32;
+    let mut mask: u64 = 0x0000_0000_ffff_ffff;
+
+    while j != 0 {
+        let mut k = 0;
+        while k < 64 {
+            let t = ((m[k] >> j) ^ m[k + j]) & mask;
+            m[k] ^= t << j;
+            m[k + j] ^= t;
+            k = (k + j + 1) & !j;
+        }
+
+        j >>= 1;
+        mask ^= mask << j;
+    }
+}
+
+/// Turns up to `L::BLOCKS` blocks into 64 lanes. Lane `c` holds bit `c + 1` of every
+/// block, and block `i` is bit `i % 64` (Lsb first) of word `i / 64`.
+pub fn to_slices<L: Lane>(blocks: &[Bits<64>]) -> [L; 64] {
+    assert!(blocks.len() <= L::BLOCKS);
+
+    let mut slices = [L::ZERO; 64];
+
+    for (k, chunk) in blocks.chunks(64).enumerate() {
+        let mut matrix = [0; 64];
+        for (row, block) in matrix.iter_mut().zip(chunk) {
+            *row = block.as_u64();
+        }
+
+        transpose(&mut matrix);
+
+        // Row j of the transposed matrix is bit j counting from the Lsb
+        for (slice, word) in slices.iter_mut().zip(matrix.into_iter().rev()) {
+            slice.set_word(k, word);
+        }
+    }
+
+    slices
+}
+
+/// Inverse of `to_slices`. Returns `count` blocks.
+pub fn from_slices<L: Lane>(slices: &[L; 64], count: usize) -> Vec<Bits<64>> {
+    assert!(count <= L::BLOCKS);
+
+    let mut blocks = Vec::with_capacity(count);
+
+    for k in 0..count.div_ceil(64) {
+        let mut matrix = [0; 64];
+        for (row, slice) in matrix.iter_mut().rev().zip(slices) {
+            *row = slice.word(k);
+        }
+
+        transpose(&mut matrix);
+
+        let take = (count - 64 * k).min(64);
+        blocks.extend(matrix[..take].iter().map(|&row| Bits::new(row)));
+    }
+
+    blocks
+}
+
+#[inline(always)]
+fn sbox_dispatch<L: Lane>(s: usize, x: [L; 6]) -> [L; 4] {
+    match s {
+        0 => sboxes::s1(x),
+        1 => sboxes::s2(x),
+        2 => sboxes::s3(x),
+        3 => sboxes::s4(x),
+        4 => sboxes::s5(x),
+        5 => sboxes::s6(x),
+        6 => sboxes::s7(x),
+        7 => sboxes::s8(x),
+        _ => unreachable!(),
+    }
+}
+
+/// Bitsliced `crate::feistel`. The key is given as one lane per bit.
+#[inline(always)]
+fn feistel<L: Lane>(r: &[L; 32], key: &[L; 48]) -> [L; 32] {
+    let mut sbox_output = [L::ZERO; 32];
+
+    for s in 0..8 {
+        let mut x = [L::ZERO; 6];
+        for (t, x) in x.iter_mut().enumerate() {
+            let i = 6 * s + t;
+            *x = r[E[i] as usize - 1] ^ key[i];
+        }
+
+        sbox_output[4 * s..4 * s + 4].copy_from_slice(&sbox_dispatch(s, x));
+    }
+
+    let mut output = [L::ZERO; 32];
+    for (out, p) in output.iter_mut().zip(P) {
+        *out = sbox_output[p as usize - 1];
+    }
+
+    output
+}
+
+/// Every key bit as a lane of all zeros or all ones
+fn key_lanes<L: Lane>(key: Bits<48>) -> [L; 48] {
+    std::array::from_fn(|i| if key.get(i + 1) { !L::ZERO } else { L::ZERO })
+}
+
+/// Bitsliced `crate::weak_encrypt`, optionally wrapped in IP and IP_INVERSE
+#[inline(always)]
+fn encrypt_slices<L: Lane>(slices: &[L; 64], keys: &[[L; 48]], ip: bool) -> [L; 64] {
+    let mut state = *slices;
+    if ip {
+        state = IP.map(|i| slices[i as usize - 1]);
+    }
+
+    let mut l: [L; 32] = state[..32].try_into().unwrap();
+    let mut r: [L; 32] = state[32..].try_into().unwrap();
+
+    for key in keys {
+        let f = feistel(&r, key);
+        for (l, f) in l.iter_mut().zip(f) {
+            *l = *l ^ f;
+        }
+        (l, r) = (r, l);
+    }
+
+    // un-swap the last swap
+    state[..32].copy_from_slice(&r);
+    state[32..].copy_from_slice(&l);
+
+    if ip {
+        let preoutput = state;
+        state = IP_INVERSE.map(|i| preoutput[i as usize - 1]);
+    }
+
+    state
+}
+
+#[inline(always)]
+fn encrypt_chunks<L: Lane>(blocks: &[Bits<64>], keys: &[Bits<48>], ip: bool) -> Vec<Bits<64>> {
+    let keys: Vec<[L; 48]> = keys.iter().map(|&key| key_lanes(key)).collect();
+    let mut output = Vec::with_capacity(blocks.len());
+
+    for chunk in blocks.chunks(L::BLOCKS) {
+        let slices = encrypt_slices(&to_slices::<L>(chunk), &keys, ip);
+        output.extend(from_slices(&slices, chunk.len()));
+    }
+
+    output
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn encrypt_chunks_avx2(blocks: &[Bits<64>], keys: &[Bits<48>], ip: bool) -> Vec<Bits<64>> {
+    encrypt_chunks::<U64x4>(blocks, keys, ip)
+}
+
+fn encrypt_chunks_dispatch(blocks: &[Bits<64>], keys: &[Bits<48>], ip: bool) -> Vec<Bits<64>> {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety: we just checked that the CPU supports AVX2
+        return unsafe { encrypt_chunks_avx2(blocks, keys, ip) };
+    }
+
+    encrypt_chunks::<u64>(blocks, keys, ip)
+}
+
+/// Same as calling `crate::weak_encrypt` on every block, with one round per key.
+pub fn weak_encrypt_many(blocks: &[Bits<64>], keys: &[Bits<48>]) -> Vec<Bits<64>> {
+    encrypt_chunks_dispatch(blocks, keys, false)
+}
+
+/// Same as calling `crate::encrypt` on every block.
+pub fn encrypt_many(blocks: &[Bits<64>], key: Bits<64>) -> Vec<Bits<64>> {
+    let keys = generate_keys::<ROUNDS>(key);
+    encrypt_chunks_dispatch(blocks, &keys, true)
+}
+
+/// Same as calling `crate::decrypt` on every block.
+pub fn decrypt_many(blocks: &[Bits<64>], key: Bits<64>) -> Vec<Bits<64>> {
+    let mut keys = generate_keys::<ROUNDS>(key);
+    keys.reverse();
+    encrypt_chunks_dispatch(blocks, &keys, true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::random;
+
+    fn random_blocks(count: usize) -> Vec<Bits<64>> {
+        (0..count).map(|_| Bits::new(random())).collect()
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut matrix = [0; 64];
+        matrix[3] = 1 << 63;
+        matrix[10] = 1;
+
+        transpose(&mut matrix);
+        assert_eq!(matrix[63], 1 << 3);
+        assert_eq!(matrix[0], 1 << 10);
+        assert_eq!(matrix.iter().map(|row| row.count_ones()).sum::<u32>(), 2);
+
+        let original: [u64; 64] = std::array::from_fn(|_| random());
+        let mut twice = original;
+        transpose(&mut twice);
+        transpose(&mut twice);
+        assert_eq!(twice, original);
+    }
+
+    #[test]
+    fn slices_round_trip() {
+        for count in [0, 1, 63, 64] {
+            let blocks = random_blocks(count);
+            assert_eq!(from_slices(&to_slices::<u64>(&blocks), count), blocks);
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        for count in [65, 200, 256] {
+            let blocks = random_blocks(count);
+            assert_eq!(from_slices(&to_slices::<U64x4>(&blocks), count), blocks);
+        }
+    }
+
+    #[test]
+    fn sbox_circuits() {
+        for x in 0..64u64 {
+            let input = Bits::<6>::new(x);
+            let lanes: [u64; 6] = std::array::from_fn(|t| input.get(t + 1) as u64);
+
+            for s in 0..8 {
+                let output = sbox_dispatch(s, lanes);
+                let value = output.iter().fold(0, |acc, &bit| acc << 1 | (bit & 1));
+                assert_eq!(value, crate::sbox(s + 1, input).as_u64());
+            }
+        }
+    }
+
+    #[test]
+    fn test_vector() {
+        let plaintext: Bits<64> = Bits::new(0x4e6f772069732074);
+        let key: Bits<64> = Bits::new(0x0123456789abcdef);
+
+        let ciphertext = encrypt_many(&[plaintext], key);
+        assert_eq!(ciphertext[0].as_u64(), 0x3fa40e8a984d4815);
+        assert_eq!(decrypt_many(&ciphertext, key)[0], plaintext);
+    }
+
+    #[test]
+    fn matches_reference() {
+        let key = Bits::new(random());
+        let keys = generate_keys::<ROUNDS>(key);
+        let blocks = random_blocks(300);
+
+        let expected: Vec<_> = blocks.iter().map(|&b| crate::encrypt(b, key)).collect();
+        assert_eq!(encrypt_many(&blocks, key), expected);
+        assert_eq!(encrypt_chunks::<u64>(&blocks, &keys, true), expected);
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // Safety: we just checked that the CPU supports AVX2
+            let avx2 = unsafe { encrypt_chunks_avx2(&blocks, &keys, true) };
+            assert_eq!(avx2, expected);
+        }
+        assert_eq!(decrypt_many(&expected, key), blocks);
+
+        let weak: [Bits<48>; 5] = keys[..5].try_into().unwrap();
+        let expected: Vec<_> = blocks
+            .iter()
+            .map(|&b| crate::weak_encrypt(b, &weak))
+            .collect();
+        assert_eq!(weak_encrypt_many(&blocks, &weak), expected);
+    }
+}
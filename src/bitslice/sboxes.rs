@@ -0,0 +1,509 @@
+//! Gate lists for the DES S-boxes, found with a search in the style of Kwan's "Reducing
+//! the Gate Count of Bitslice DES". Inputs and outputs are Msb first, like `crate::sbox`.
+use super::Lane;
+
+/// S1 in 62 gates
+#[inline(always)]
+pub(super) fn s1<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = !x3;
+    let t2 = x5 ^ x6;
+    let t3 = x4 & t2;
+    let t4 = t1 ^ t3;
+    let t5 = x3 | t2;
+    let t6 = x6 | t4;
+    let t7 = t6 ^ t2;
+    let t8 = x4 & t7;
+    let t9 = t5 ^ t8;
+    let t10 = !x2;
+    let t11 = t10 & t9;
+    let t12 = t4 ^ t11;
+    let t13 = t9 | t10;
+    let t14 = t6 ^ t12;
+    let t15 = x5 & t14;
+    let t16 = t13 ^ t15;
+    let t17 = x3 ^ x5;
+    let t18 = t17 | t15;
+    let t19 = x4 & t18;
+    let t20 = t16 ^ t19;
+    let t21 = x1 & t20;
+    let t22 = t12 ^ t21;
+    let t23 = t4 ^ t5;
+    let t24 = x2 & t23;
+    let t25 = x4 ^ t24;
+    let t26 = x3 ^ x6;
+    let t27 = t26 & t18;
+    let t28 = x5 & t27;
+    let t29 = t25 ^ t28;
+    let t30 = t5 ^ t17;
+    let t31 = t30 | t14;
+    let t32 = t3 | t24;
+    let t33 = t32 ^ t30;
+    let t34 = x4 & t33;
+    let t35 = t31 ^ t34;
+    let t36 = x1 & t35;
+    let t37 = t29 ^ t36;
+    let t38 = t21 ^ t34;
+    let t39 = t38 & t36;
+    let t40 = x4 & t16;
+    let t41 = t40 | t4;
+    let t42 = x2 & t41;
+    let t43 = t39 ^ t42;
+    let t44 = t9 & t19;
+    let t45 = t44 | t2;
+    let t46 = t1 & t41;
+    let t47 = t45 ^ t46;
+    let t48 = !x1;
+    let t49 = t48 & t47;
+    let t50 = t43 ^ t49;
+    let t51 = t22 ^ t37;
+    let t52 = t51 ^ t50;
+    let t53 = t38 | t50;
+    let t54 = t53 ^ t2;
+    let t55 = x5 & t54;
+    let t56 = t52 ^ t55;
+    let t57 = x4 | t12;
+    let t58 = t15 & t33;
+    let t59 = t48 & t58;
+    let t60 = t57 ^ t59;
+    let t61 = t10 & t60;
+    let t62 = t56 ^ t61;
+    [t50, t62, t22, t37]
+}
+
+/// S2 in 56 gates
+#[inline(always)]
+pub(super) fn s2<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x5 ^ x6;
+    let t2 = t1 | x3;
+    let t3 = x6 & t2;
+    let t4 = x4 ^ t3;
+    let t5 = !t2;
+    let t6 = t1 | t4;
+    let t7 = x5 & t6;
+    let t8 = t5 ^ t7;
+    let t9 = !x1;
+    let t10 = t9 & t8;
+    let t11 = t4 ^ t10;
+    let t12 = x3 ^ t11;
+    let t13 = t12 | t9;
+    let t14 = x6 & t13;
+    let t15 = x1 ^ t14;
+    let t16 = t9 | t15;
+    let t17 = t16 ^ t12;
+    let t18 = x5 & t17;
+    let t19 = t15 ^ t18;
+    let t20 = x2 & t19;
+    let t21 = t11 ^ t20;
+    let t22 = x2 ^ t13;
+    let t23 = x5 & t12;
+    let t24 = t22 ^ t23;
+    let t25 = t11 ^ t13;
+    let t26 = t25 | t20;
+    let t27 = x6 & t26;
+    let t28 = t24 ^ t27;
+    let t29 = t20 & t28;
+    let t30 = t29 ^ t1;
+    let t31 = t9 & t6;
+    let t32 = t30 ^ t31;
+    let t33 = !x3;
+    let t34 = t33 & t32;
+    let t35 = t28 ^ t34;
+    let t36 = t4 ^ t7;
+    let t37 = t36 ^ t9;
+    let t38 = t6 ^ t8;
+    let t39 = t38 | t28;
+    let t40 = x2 & t39;
+    let t41 = t37 ^ t40;
+    let t42 = t6 ^ t40;
+    let t43 = t42 & t3;
+    let t44 = x3 & t43;
+    let t45 = t41 ^ t44;
+    let t46 = x3 ^ t9;
+    let t47 = t46 ^ x6;
+    let t48 = t12 ^ t47;
+    let t49 = x2 & t48;
+    let t50 = t47 ^ t49;
+    let t51 = t9 & t48;
+    let t52 = t51 ^ t42;
+    let t53 = x2 & t16;
+    let t54 = t52 ^ t53;
+    let t55 = x5 & t54;
+    let t56 = t50 ^ t55;
+    [t56, t45, t35, t21]
+}
+
+/// S3 in 59 gates
+#[inline(always)]
+pub(super) fn s3<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x3 ^ x6;
+    let t2 = x4 | x6;
+    let t3 = x1 & t2;
+    let t4 = t1 ^ t3;
+    let t5 = x3 ^ x4;
+    let t6 = t5 | x1;
+    let t7 = !x5;
+    let t8 = t7 & t6;
+    let t9 = t4 ^ t8;
+    let t10 = t1 ^ t6;
+    let t11 = t10 & t9;
+    let t12 = x4 & t4;
+    let t13 = t11 ^ t12;
+    let t14 = x1 & t13;
+    let t15 = x2 ^ t14;
+    let t16 = x2 & t15;
+    let t17 = t9 ^ t16;
+    let t18 = t1 ^ t7;
+    let t19 = t18 ^ x4;
+    let t20 = t8 | t11;
+    let t21 = t20 & x3;
+    let t22 = !x2;
+    let t23 = t22 & t21;
+    let t24 = t19 ^ t23;
+    let t25 = t12 | t17;
+    let t26 = t25 | t8;
+    let t27 = t1 ^ t26;
+    let t28 = t27 & t9;
+    let t29 = x3 & t28;
+    let t30 = t26 ^ t29;
+    let t31 = !x1;
+    let t32 = t31 & t30;
+    let t33 = t24 ^ t32;
+    let t34 = t9 | t33;
+    let t35 = t31 & t34;
+    let t36 = t1 ^ t35;
+    let t37 = t5 | t29;
+    let t38 = x5 & t37;
+    let t39 = t36 ^ t38;
+    let t40 = x1 | t35;
+    let t41 = x6 & t35;
+    let t42 = t41 ^ x6;
+    let t43 = x4 & t42;
+    let t44 = t40 ^ t43;
+    let t45 = t22 & t44;
+    let t46 = t39 ^ t45;
+    let t47 = t8 & t33;
+    let t48 = t47 | t18;
+    let t49 = x6 | t38;
+    let t50 = t49 ^ t44;
+    let t51 = t22 & t50;
+    let t52 = t48 ^ t51;
+    let t53 = t24 ^ t43;
+    let t54 = t53 | t10;
+    let t55 = t25 ^ t43;
+    let t56 = t22 & t55;
+    let t57 = t54 ^ t56;
+    let t58 = t31 & t57;
+    let t59 = t52 ^ t58;
+    [t33, t46, t59, t17]
+}
+
+/// S4 in 45 gates
+#[inline(always)]
+pub(super) fn s4<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x1 | x4;
+    let t2 = t1 ^ x5;
+    let t3 = x1 & x4;
+    let t4 = t3 | x5;
+    let t5 = x3 & t4;
+    let t6 = t2 ^ t5;
+    let t7 = x3 | t3;
+    let t8 = x5 & t6;
+    let t9 = !x1;
+    let t10 = t9 & t8;
+    let t11 = t7 ^ t10;
+    let t12 = x2 & t11;
+    let t13 = t6 ^ t12;
+    let t14 = x2 ^ t9;
+    let t15 = t14 ^ t1;
+    let t16 = t13 | t14;
+    let t17 = x3 & t16;
+    let t18 = t15 ^ t17;
+    let t19 = t11 & t16;
+    let t20 = t19 ^ t3;
+    let t21 = x5 & t20;
+    let t22 = t18 ^ t21;
+    let t23 = x6 & t22;
+    let t24 = t13 ^ t23;
+    let t25 = t9 | t14;
+    let t26 = t25 ^ t12;
+    let t27 = x4 & t2;
+    let t28 = t26 ^ t27;
+    let t29 = x5 | t26;
+    let t30 = t29 ^ x2;
+    let t31 = x3 & t30;
+    let t32 = t28 ^ t31;
+    let t33 = t2 ^ t30;
+    let t34 = t33 ^ t20;
+    let t35 = t26 | t28;
+    let t36 = t35 ^ t19;
+    let t37 = !x3;
+    let t38 = t37 & t36;
+    let t39 = t34 ^ t38;
+    let t40 = x6 & t39;
+    let t41 = t32 ^ t40;
+    let t42 = x6 ^ t39;
+    let t43 = t42 ^ t41;
+    let t44 = x6 ^ t22;
+    let t45 = t44 ^ t24;
+    [t24, t45, t43, t41]
+}
+
+/// S5 in 63 gates
+#[inline(always)]
+pub(super) fn s5<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x4 | x6;
+    let t2 = t1 & x2;
+    let t3 = x3 ^ t1;
+    let t4 = t3 ^ x4;
+    let t5 = x3 & t4;
+    let t6 = t2 ^ t5;
+    let t7 = x4 | t5;
+    let t8 = t7 ^ x6;
+    let t9 = !t4;
+    let t10 = x2 & t9;
+    let t11 = t8 ^ t10;
+    let t12 = x5 & t11;
+    let t13 = t6 ^ t12;
+    let t14 = t10 ^ t12;
+    let t15 = t14 | t8;
+    let t16 = t9 | t13;
+    let t17 = x5 & t16;
+    let t18 = x3 ^ t17;
+    let t19 = x4 & t18;
+    let t20 = t15 ^ t19;
+    let t21 = x1 & t20;
+    let t22 = t13 ^ t21;
+    let t23 = x1 | t15;
+    let t24 = t23 ^ t9;
+    let t25 = x1 ^ t3;
+    let t26 = t25 | t22;
+    let t27 = x2 & t26;
+    let t28 = t24 ^ t27;
+    let t29 = t12 ^ t18;
+    let t30 = t29 | x1;
+    let t31 = x1 ^ t27;
+    let t32 = t31 & t1;
+    let t33 = x2 & t32;
+    let t34 = t30 ^ t33;
+    let t35 = x5 & t34;
+    let t36 = t28 ^ t35;
+    let t37 = x1 ^ t20;
+    let t38 = t18 ^ t32;
+    let t39 = t9 | t35;
+    let t40 = x4 & t39;
+    let t41 = t38 ^ t40;
+    let t42 = x5 & t41;
+    let t43 = t37 ^ t42;
+    let t44 = t11 | t41;
+    let t45 = t44 ^ t31;
+    let t46 = t5 ^ t13;
+    let t47 = t46 ^ t7;
+    let t48 = x1 & t47;
+    let t49 = t45 ^ t48;
+    let t50 = x2 & t49;
+    let t51 = t43 ^ t50;
+    let t52 = x4 ^ t45;
+    let t53 = x6 | t34;
+    let t54 = x3 & t53;
+    let t55 = t52 ^ t54;
+    let t56 = t37 | t50;
+    let t57 = t56 ^ t44;
+    let t58 = t36 | t38;
+    let t59 = t58 ^ t52;
+    let t60 = x3 & t59;
+    let t61 = t57 ^ t60;
+    let t62 = x1 & t61;
+    let t63 = t55 ^ t62;
+    [t63, t51, t36, t22]
+}
+
+/// S6 in 58 gates
+#[inline(always)]
+pub(super) fn s6<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x1 | x5;
+    let t2 = t1 ^ x2;
+    let t3 = x1 ^ x5;
+    let t4 = x3 & t3;
+    let t5 = t2 ^ t4;
+    let t6 = x3 | t5;
+    let t7 = t6 ^ t3;
+    let t8 = !x4;
+    let t9 = t8 & t7;
+    let t10 = t5 ^ t9;
+    let t11 = t2 | t10;
+    let t12 = t11 ^ t7;
+    let t13 = x2 | t12;
+    let t14 = x4 & t13;
+    let t15 = t6 ^ t14;
+    let t16 = x5 & t15;
+    let t17 = t12 ^ t16;
+    let t18 = x6 & t17;
+    let t19 = t10 ^ t18;
+    let t20 = !t7;
+    let t21 = x3 | x5;
+    let t22 = t8 & t21;
+    let t23 = t20 ^ t22;
+    let t24 = t2 ^ t23;
+    let t25 = x4 & t24;
+    let t26 = t21 ^ t25;
+    let t27 = t11 & t13;
+    let t28 = t27 ^ t24;
+    let t29 = x1 & t28;
+    let t30 = t26 ^ t29;
+    let t31 = x6 & t30;
+    let t32 = t23 ^ t31;
+    let t33 = t8 ^ t20;
+    let t34 = t33 ^ x3;
+    let t35 = x1 ^ t1;
+    let t36 = t8 & t35;
+    let t37 = t5 ^ t36;
+    let t38 = x2 & t37;
+    let t39 = t34 ^ t38;
+    let t40 = t24 | t26;
+    let t41 = t9 | t28;
+    let t42 = x5 & t41;
+    let t43 = t40 ^ t42;
+    let t44 = x6 & t43;
+    let t45 = t39 ^ t44;
+    let t46 = t15 | t45;
+    let t47 = t46 ^ t24;
+    let t48 = t6 | t33;
+    let t49 = t48 ^ t39;
+    let t50 = x1 & t49;
+    let t51 = t47 ^ t50;
+    let t52 = t18 | t41;
+    let t53 = t6 ^ t23;
+    let t54 = !x2;
+    let t55 = t54 & t53;
+    let t56 = t52 ^ t55;
+    let t57 = x6 & t56;
+    let t58 = t51 ^ t57;
+    [t32, t58, t45, t19]
+}
+
+/// S7 in 59 gates
+#[inline(always)]
+pub(super) fn s7<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = x2 ^ x4;
+    let t2 = x5 ^ t1;
+    let t3 = t2 & x4;
+    let t4 = x3 & t3;
+    let t5 = t1 ^ t4;
+    let t6 = x3 ^ t4;
+    let t7 = t6 ^ x1;
+    let t8 = x4 ^ t3;
+    let t9 = x2 & t8;
+    let t10 = t7 ^ t9;
+    let t11 = x1 & t10;
+    let t12 = t5 ^ t11;
+    let t13 = x5 ^ t8;
+    let t14 = t13 ^ t10;
+    let t15 = t12 | t14;
+    let t16 = x3 & t15;
+    let t17 = x5 ^ t16;
+    let t18 = x1 & t17;
+    let t19 = t14 ^ t18;
+    let t20 = !x6;
+    let t21 = t20 & t19;
+    let t22 = t12 ^ t21;
+    let t23 = x3 ^ x6;
+    let t24 = t23 ^ x5;
+    let t25 = x2 & x4;
+    let t26 = t24 ^ t25;
+    let t27 = x3 & t5;
+    let t28 = t20 & t27;
+    let t29 = t26 ^ t28;
+    let t30 = t8 | t18;
+    let t31 = t30 ^ t5;
+    let t32 = t8 ^ t29;
+    let t33 = t32 ^ t27;
+    let t34 = x6 & t33;
+    let t35 = t31 ^ t34;
+    let t36 = x1 & t35;
+    let t37 = t29 ^ t36;
+    let t38 = t15 ^ t20;
+    let t39 = t38 ^ t13;
+    let t40 = !x1;
+    let t41 = t40 & t39;
+    let t42 = t29 ^ t41;
+    let t43 = t26 & t40;
+    let t44 = t3 ^ t11;
+    let t45 = t44 | t27;
+    let t46 = x6 & t45;
+    let t47 = t43 ^ t46;
+    let t48 = x2 & t47;
+    let t49 = t42 ^ t48;
+    let t50 = x6 ^ t31;
+    let t51 = t50 | t22;
+    let t52 = x6 & t51;
+    let t53 = t14 ^ t52;
+    let t54 = !t45;
+    let t55 = t37 & t51;
+    let t56 = x1 & t55;
+    let t57 = t54 ^ t56;
+    let t58 = x2 & t57;
+    let t59 = t53 ^ t58;
+    [t37, t49, t22, t59]
+}
+
+/// S8 in 56 gates
+#[inline(always)]
+pub(super) fn s8<L: Lane>([x1, x2, x3, x4, x5, x6]: [L; 6]) -> [L; 4] {
+    let t1 = !x4;
+    let t2 = t1 ^ x6;
+    let t3 = !x3;
+    let t4 = x5 & t3;
+    let t5 = t2 ^ t4;
+    let t6 = x4 | x5;
+    let t7 = t6 ^ t3;
+    let t8 = x2 & t7;
+    let t9 = t5 ^ t8;
+    let t10 = !x5;
+    let t11 = x6 | t5;
+    let t12 = t3 & t11;
+    let t13 = t10 ^ t12;
+    let t14 = t3 | t11;
+    let t15 = t14 ^ t8;
+    let t16 = x2 & t15;
+    let t17 = t13 ^ t16;
+    let t18 = x1 & t17;
+    let t19 = t9 ^ t18;
+    let t20 = t17 ^ t19;
+    let t21 = x2 & t5;
+    let t22 = t7 | t16;
+    let t23 = x1 & t22;
+    let t24 = t21 ^ t23;
+    let t25 = !x6;
+    let t26 = t25 & t24;
+    let t27 = t20 ^ t26;
+    let t28 = x1 ^ t21;
+    let t29 = x2 ^ t19;
+    let t30 = x6 & t29;
+    let t31 = t28 ^ t30;
+    let t32 = x5 & t31;
+    let t33 = t27 ^ t32;
+    let t34 = t16 | t21;
+    let t35 = t34 ^ t1;
+    let t36 = t10 & t7;
+    let t37 = t35 ^ t36;
+    let t38 = !t8;
+    let t39 = t25 & t38;
+    let t40 = t33 ^ t39;
+    let t41 = x5 & t40;
+    let t42 = t11 ^ t41;
+    let t43 = x1 & t42;
+    let t44 = t37 ^ t43;
+    let t45 = x2 ^ t30;
+    let t46 = t45 ^ t7;
+    let t47 = t20 ^ t44;
+    let t48 = t47 | x6;
+    let t49 = x1 & t48;
+    let t50 = t46 ^ t49;
+    let t51 = t17 & t45;
+    let t52 = x2 & t14;
+    let t53 = x5 & t52;
+    let t54 = t51 ^ t53;
+    let t55 = x4 & t54;
+    let t56 = t50 ^ t55;
+    [t33, t19, t44, t56]
+}
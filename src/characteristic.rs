@@ -0,0 +1,557 @@
+//! Search for the best differential characteristics of reduced-round DES.
+//!
+//! This is Matsui's branch-and-bound search ("On correlation between the order of
+//! S-boxes and the strength of DES") over the difference distribution tables. Write a_i
+//! for the input difference of the round function in round i and y_i for its output
+//! difference. Then a_(i+1) = a_(i-1) ^ y_i, so only the first two rounds are free and
+//! every later round input is fixed by the rounds before it. A branch is dropped as soon
+//! as its probability times the best probability of the remaining rounds can't beat the
+//! characteristics found so far.
+//!
+//! Differences live in the domain of `weak_encrypt`, so the input is L0||R0 after IP and
+//! the output is R_r||L_r, like the values `jupiter` feeds to
+//! `estimate_diff_probability`.
+use crate::ddt::Ddt;
+use crate::trail::sbox_output_masks;
+use crate::{fast_expand, fast_p_permute, sbox_lookup, Bits, SBOX};
+use std::fmt;
+
+/// One round of a characteristic
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RoundDifference {
+    /// Difference going into the round function (the right half)
+    pub input: Bits<32>,
+    /// Difference coming out of the round function (after P)
+    pub output: Bits<32>,
+    pub probability: f64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Characteristic {
+    /// Plaintext difference L0||R0
+    pub input: Bits<64>,
+    pub rounds: Vec<RoundDifference>,
+    /// Ciphertext difference R_r||L_r, as `weak_encrypt` outputs it
+    pub output: Bits<64>,
+    pub probability: f64,
+}
+
+impl RoundDifference {
+    /// The probability of the round under the least favourable round key.
+    ///
+    /// `probability` is the average over all keys. Neighbouring S-boxes share two input
+    /// bits through E, so when both are active the xor of the round key bits at that
+    /// overlap decides which pairs of inputs they can see together, and with it how
+    /// likely the round is. Rounds with all 8 S-boxes active only get their average.
+    pub fn worst_probability(&self) -> f64 {
+        let inputs = sbox_inputs(self.input.as_u64() as u32);
+        let outputs = sbox_output_masks(self.output.as_u64() as u32);
+
+        // Runs of neighbouring active S-boxes, which depend on disjoint key bits
+        let Some(start) = (0..8).find(|&j| inputs[j] == 0) else {
+            return self.probability;
+        };
+        let mut runs: Vec<Vec<usize>> = vec![Vec::new()];
+        for j in (1..=8).map(|j| (start + j) % 8) {
+            if inputs[j] != 0 {
+                runs.last_mut().unwrap().push(j);
+            } else if !runs.last().unwrap().is_empty() {
+                runs.push(Vec::new());
+            }
+        }
+
+        runs.iter()
+            .filter(|run| !run.is_empty())
+            .map(|run| {
+                // Which inputs x of every S-box give the output difference
+                let right: Vec<[bool; 64]> = run
+                    .iter()
+                    .map(|&j| {
+                        std::array::from_fn(|x| {
+                            sbox_lookup(&SBOX[j], x) ^ sbox_lookup(&SBOX[j], x ^ inputs[j] as usize)
+                                == outputs[j]
+                        })
+                    })
+                    .collect();
+
+                // For every xor of the key bits at the overlaps, count the input vectors
+                // where the last 2 bits of an input xor the first 2 of the next one give
+                // that xor and all S-boxes see a right pair
+                let worst = (0..1usize << (2 * (run.len() - 1)))
+                    .map(|overlaps| {
+                        let mut counts = right[0].map(|right| right as u64);
+                        for (i, right) in right.iter().enumerate().skip(1) {
+                            let overlap = overlaps >> (2 * (i - 1)) & 3;
+                            let mut sums = [0; 4];
+                            for (x, count) in counts.iter().enumerate() {
+                                sums[x & 3] += count;
+                            }
+                            counts =
+                                std::array::from_fn(|x| right[x] as u64 * sums[x >> 4 ^ overlap]);
+                        }
+                        counts.iter().sum::<u64>()
+                    })
+                    .min()
+                    .unwrap();
+
+                worst as f64 / (64.0 * 16f64.powi(run.len() as i32 - 1))
+            })
+            .product()
+    }
+}
+
+impl Characteristic {
+    /// The probability of the characteristic under the least favourable round keys, see
+    /// `RoundDifference::worst_probability`.
+    pub fn worst_probability(&self) -> f64 {
+        self.rounds
+            .iter()
+            .map(RoundDifference::worst_probability)
+            .product()
+    }
+
+    /// Whether the characteristic ends in the state it started with, so it can be
+    /// concatenated with itself.
+    pub fn is_iterative(&self) -> bool {
+        let (l, r) = self.output.split::<32>();
+        r.concat(l) == self.input
+    }
+
+    /// Repeats an iterative characteristic until it covers `rounds` rounds, the way
+    /// Biham and Shamir build long characteristics from the 2 round ones.
+    pub fn iterate(&self, rounds: usize) -> Characteristic {
+        assert!(self.is_iterative());
+
+        let rounds: Vec<RoundDifference> =
+            self.rounds.iter().cycle().take(rounds).copied().collect();
+
+        let (mut l, mut r) = self.input.split::<32>();
+        for round in &rounds {
+            (l, r) = (r, l ^ round.output);
+        }
+
+        Characteristic {
+            input: self.input,
+            output: r.concat(l),
+            probability: rounds.iter().map(|round| round.probability).product(),
+            rounds,
+        }
+    }
+}
+
+impl fmt::Display for Characteristic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "input  {:016x}", self.input.as_u64())?;
+
+        for (i, round) in self.rounds.iter().enumerate() {
+            writeln!(
+                f,
+                "round {:2}  {:08x} -> {:08x}  p = 2^{:.2}",
+                i + 1,
+                round.input.as_u64(),
+                round.output.as_u64(),
+                round.probability.log2()
+            )?;
+        }
+
+        writeln!(f, "output {:016x}", self.output.as_u64())?;
+        writeln!(f, "p = 2^{:.2}", self.probability.log2())
+    }
+}
+
+/// The S-box inputs of the round function for the input difference `a`
+fn sbox_inputs(a: u32) -> [u8; 8] {
+    let expanded = fast_expand(Bits::new(a as u64)).as_u64();
+    std::array::from_fn(|j| (expanded >> (42 - 6 * j) & 0x3f) as u8)
+}
+
+/// `search_with` settles for fewer than k characteristics once it has found every one
+/// that is at most this many times less likely than the best
+const SPREAD: f64 = 4.0;
+
+struct Search<'a> {
+    rounds: usize,
+    k: usize,
+    iterative: bool,
+    /// `transitions[j][x]` are the possible outputs of S-box j + 1 for the input x with
+    /// their probability, most likely first.
+    transitions: &'a [Vec<Vec<(u8, f64)>>],
+    /// `bounds[n]` is the probability of the best n round characteristic
+    bounds: Vec<f64>,
+    threshold: f64,
+    /// Best characteristics so far, most likely first
+    found: Vec<Characteristic>,
+    /// (a_i, y_i, probability) of the rounds on the current branch
+    path: Vec<(u32, u32, f64)>,
+}
+
+impl Search<'_> {
+    fn worth_exploring(&self, bound: f64) -> bool {
+        bound >= self.threshold
+            && (self.found.len() < self.k || bound > self.found[self.k - 1].probability)
+    }
+
+    /// Round `i` (1-indexed) after the rounds before it reached probability `p`
+    fn round(&mut self, i: usize, p: f64) {
+        if i > self.rounds {
+            self.leaf();
+        } else if i <= 2 {
+            self.free_sbox(i, 0, 0, 0, p, 1.0);
+        } else {
+            let a = self.path[i - 3].0 ^ self.path[i - 2].1;
+            self.fixed_sbox(i, a, &sbox_inputs(a), 0, 0, p, 1.0);
+        }
+    }
+
+    /// Picks the input and output of S-box `j` (0-indexed) in one of the two free rounds.
+    /// `inputs` holds the S-box inputs chosen so far, 6 bits each, `s` their outputs and
+    /// `q` the probability of the round so far.
+    fn free_sbox(&mut self, i: usize, j: usize, inputs: u64, s: u32, p: f64, q: f64) {
+        if j == 8 {
+            // The middle 4 bits of every S-box input make up the round input
+            let a = (0..8).fold(0, |a, j| a << 4 | (inputs >> (43 - 6 * j) & 0xf) as u32);
+            self.end_round(a, s, p, q);
+            return;
+        }
+
+        let bound = p * self.bounds[self.rounds - i];
+        let previous = (inputs & 0x3f) as u8;
+        let first = (inputs >> (6 * j).saturating_sub(6)) as u8 & 0x3f;
+
+        for x in 0..64u8 {
+            // Neighbouring S-boxes share two input bits through E, and S8 wraps around to S1
+            if j > 0 && x >> 4 != previous & 3 {
+                continue;
+            }
+            if j == 7 && x & 3 != first >> 4 {
+                continue;
+            }
+
+            // The output of round 1 only changes L0 and none of the later rounds, so only
+            // the best one is searched here and `leaf` fills in the others.
+            let transitions = &self.transitions[j][x as usize];
+            let transitions = if i == 1 {
+                &transitions[..1]
+            } else {
+                transitions
+            };
+
+            for &(y, t) in transitions {
+                if !self.worth_exploring(bound * q * t) {
+                    break;
+                }
+                let inputs = inputs << 6 | x as u64;
+                self.free_sbox(i, j + 1, inputs, s << 4 | y as u32, p, q * t);
+            }
+        }
+    }
+
+    /// Picks the output of S-box `j` (0-indexed) in a round with a fixed input
+    #[allow(clippy::too_many_arguments)]
+    fn fixed_sbox(&mut self, i: usize, a: u32, inputs: &[u8; 8], j: usize, s: u32, p: f64, q: f64) {
+        if j == 8 {
+            self.end_round(a, s, p, q);
+            return;
+        }
+
+        // Every remaining S-box takes at best its most likely transition
+        let bound = p * q * self.bounds[self.rounds - i] * self.best_transitions(inputs, j + 1);
+
+        for &(y, t) in &self.transitions[j][inputs[j] as usize] {
+            if !self.worth_exploring(bound * t) {
+                break;
+            }
+            self.fixed_sbox(i, a, inputs, j + 1, s << 4 | y as u32, p, q * t);
+        }
+    }
+
+    /// Probability of the most likely transitions of the S-boxes from `j` on
+    fn best_transitions(&self, inputs: &[u8; 8], j: usize) -> f64 {
+        (j..8)
+            .map(|m| self.transitions[m][inputs[m] as usize][0].1)
+            .product()
+    }
+
+    fn end_round(&mut self, a: u32, s: u32, p: f64, q: f64) {
+        // Skip the trivial characteristic where nothing differs
+        if self.path.len() == 1 && a == 0 && self.path[0].0 == 0 {
+            return;
+        }
+
+        let y = fast_p_permute(Bits::new(s as u64)).as_u64() as u32;
+
+        self.path.push((a, y, q));
+        self.round(self.path.len() + 1, p * q);
+        self.path.pop();
+    }
+
+    /// Goes through the outputs of round 1 for a complete branch
+    fn leaf(&mut self) {
+        let rest: f64 = self.path[1..].iter().map(|r| r.2).product();
+        let inputs = sbox_inputs(self.path[0].0);
+        self.first_round_sbox(&inputs, 0, 0, 1.0, rest);
+    }
+
+    fn first_round_sbox(&mut self, inputs: &[u8; 8], j: usize, s: u32, q: f64, rest: f64) {
+        if j == 8 {
+            let y = fast_p_permute(Bits::new(s as u64)).as_u64() as u32;
+            self.record(y, q, rest);
+            return;
+        }
+
+        let bound = rest * q * self.best_transitions(inputs, j + 1);
+
+        for &(y, t) in &self.transitions[j][inputs[j] as usize] {
+            if !self.worth_exploring(bound * t) {
+                break;
+            }
+            self.first_round_sbox(inputs, j + 1, s << 4 | y as u32, q * t, rest);
+        }
+    }
+
+    /// Adds the branch with `y1` and `q1` as output and probability of round 1
+    fn record(&mut self, y1: u32, q1: f64, rest: f64) {
+        let a = |i: usize| self.path[i - 1].0;
+        let y = |i: usize| if i == 1 { y1 } else { self.path[i - 1].1 };
+        let r = self.rounds;
+
+        let l0 = a(2) ^ y(1);
+        let r0 = a(1);
+        let lr = a(r);
+        let rr = a(r - 1) ^ y(r);
+
+        if self.iterative && (lr, rr) != (l0, r0) {
+            return;
+        }
+
+        let mut rounds: Vec<RoundDifference> = self
+            .path
+            .iter()
+            .map(|&(a, y, probability)| RoundDifference {
+                input: Bits::new(a as u64),
+                output: Bits::new(y as u64),
+                probability,
+            })
+            .collect();
+        rounds[0].output = Bits::new(y1 as u64);
+        rounds[0].probability = q1;
+
+        let probability = q1 * rest;
+        let characteristic = Characteristic {
+            input: Bits::<32>::new(l0 as u64).concat(Bits::<32>::new(r0 as u64)),
+            rounds,
+            output: Bits::<32>::new(rr as u64).concat(Bits::<32>::new(lr as u64)),
+            probability,
+        };
+
+        let position = self
+            .found
+            .iter()
+            .position(|c| c.probability < probability)
+            .unwrap_or(self.found.len());
+        self.found.insert(position, characteristic);
+        self.found.truncate(self.k);
+    }
+}
+
+fn search_with(rounds: usize, k: usize, iterative: bool, bounds: &[f64]) -> Vec<Characteristic> {
+    let transitions: Vec<Vec<Vec<(u8, f64)>>> = (1..=8)
+        .map(|i| {
+            let ddt = Ddt::des(i);
+            (0..64)
+                .map(|x| {
+                    let outputs = ddt.outputs(x).into_iter();
+                    outputs.map(|(y, count)| (y, count as f64 / 64.0)).collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut search = Search {
+        rounds,
+        k,
+        iterative,
+        transitions: &transitions,
+        bounds: bounds.to_vec(),
+        threshold: 1.0,
+        found: Vec::new(),
+        path: Vec::with_capacity(rounds),
+    };
+
+    // Start with a threshold close to the best characteristic and lower it until there
+    // are k characteristics above it. Every pass is much cheaper than the next one, so
+    // stop once the threshold is `SPREAD` times below the best one even if there are fewer
+    // (as with iterative characteristics, where k can easily be more than there are).
+    search.threshold = bounds[rounds - 1] / 4.0;
+    loop {
+        search.round(1, 1.0);
+
+        let spread = |best: &Characteristic| search.threshold * SPREAD < best.probability;
+        if search.found.len() == k || search.found.first().is_some_and(spread) {
+            break;
+        }
+        search.found.clear();
+        search.threshold /= 2.0;
+    }
+
+    search.found
+}
+
+/// The probability of the best characteristic for 0 up to `rounds - 1` rounds, where the
+/// trivial zero and one round characteristics have probability 1.
+fn bounds(rounds: usize) -> Vec<f64> {
+    let mut bounds = vec![1.0, 1.0];
+
+    for n in 2..rounds {
+        let best = search_with(n, 1, false, &bounds);
+        bounds.push(best[0].probability);
+    }
+
+    bounds
+}
+
+/// The `k` most likely characteristics over `rounds` rounds (at least 2), most likely
+/// first, or fewer if there aren't k within a factor of 4 of the best one.
+/// Characteristics with the same probability come out in no particular order.
+pub fn search(rounds: usize, k: usize) -> Vec<Characteristic> {
+    assert!(
+        rounds >= 2,
+        "Every one round characteristic has probability 1."
+    );
+    assert!(k > 0);
+
+    search_with(rounds, k, false, &bounds(rounds))
+}
+
+/// Same as `search`, but only returns iterative characteristics (see
+/// `Characteristic::is_iterative`).
+pub fn search_iterative(rounds: usize, k: usize) -> Vec<Characteristic> {
+    assert!(
+        rounds >= 2,
+        "Every one round characteristic has probability 1."
+    );
+    assert!(k > 0);
+
+    search_with(rounds, k, true, &bounds(rounds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate_keys, weak_encrypt};
+    use rand::random;
+
+    #[test]
+    fn best_probabilities() {
+        // Matsui's table of the best characteristics of reduced-round DES
+        let expected = [-2.0, -4.0, -9.61];
+
+        for (rounds, expected) in (2..).zip(expected) {
+            let best = &search(rounds, 1)[0];
+            assert!((best.probability.log2() - expected).abs() < 0.01);
+            assert_eq!(best.rounds.len(), rounds);
+        }
+    }
+
+    #[test]
+    fn iterative_characteristic() {
+        // Biham and Shamir's 19600000 characteristic with probability 1/234
+        let found = search_iterative(2, 4);
+        let best = &found[0];
+
+        assert!(best.is_iterative());
+        assert_eq!(best.probability, (14.0 * 8.0 * 10.0) / (64.0 * 64.0 * 64.0));
+        assert!(found
+            .iter()
+            .any(|c| c.rounds[0].input.as_u64() == 0x19600000));
+        assert!(found
+            .iter()
+            .any(|c| c.rounds[0].input.as_u64() == 0x1b600000));
+    }
+
+    #[test]
+    fn worst_probability() {
+        let found = search_iterative(2, 6);
+        let round = |input: u64| {
+            let c = found.iter().find(|c| c.rounds[0].input.as_u64() == input);
+            c.unwrap().rounds[0]
+        };
+
+        // S1, S2 and S3 are active next to each other, and measured over many keys the
+        // worst ones get about 0.4 of the average
+        let round_a = round(0x19600000);
+        let worst = round_a.worst_probability();
+        assert!(worst > 0.35 * round_a.probability && worst < 0.45 * round_a.probability);
+
+        // The same pattern on S3, S4 and S5 happens to work as well for every key
+        let round_b = round(0x00196000);
+        assert_eq!(round_b.worst_probability(), round_b.probability);
+
+        let trivial = RoundDifference {
+            input: Bits::new(0),
+            output: Bits::new(0),
+            probability: 1.0,
+        };
+        assert_eq!(trivial.worst_probability(), 1.0);
+    }
+
+    #[test]
+    #[ignore = "takes a while without optimizations"]
+    fn fewer_than_k() {
+        // There aren't nearly this many iterative characteristics close to the best one
+        let found = search_iterative(2, 10000);
+
+        assert!(found.len() < 10000);
+        assert!(found.iter().all(Characteristic::is_iterative));
+    }
+
+    #[test]
+    fn iterated_characteristic() {
+        let two_rounds = search_iterative(2, 1).remove(0);
+        let seven_rounds = two_rounds.iterate(7);
+
+        assert_eq!(seven_rounds.rounds.len(), 7);
+        assert!((seven_rounds.probability.log2() + 23.61).abs() < 0.01);
+        assert_eq!(two_rounds.iterate(4).output, two_rounds.output);
+
+        // Ends one round into the next iteration
+        let (l, r) = two_rounds.input.split::<32>();
+        let next = two_rounds.rounds[0].output;
+        assert_eq!(seven_rounds.output, (l ^ next).concat(r));
+    }
+
+    #[test]
+    fn top_k_is_sorted() {
+        let found = search(3, 10);
+
+        assert_eq!(found.len(), 10);
+        assert!(found
+            .windows(2)
+            .all(|w| w[0].probability >= w[1].probability));
+
+        for c in &found {
+            let product: f64 = c.rounds.iter().map(|r| r.probability).product();
+            assert_eq!(product, c.probability);
+        }
+    }
+
+    #[test]
+    fn characteristic_holds_empirically() {
+        let best = &search(3, 1)[0];
+        assert_eq!(best.probability, 1.0 / 16.0);
+
+        let keys = generate_keys::<3>(Bits::new(random()));
+        let total = 20_000;
+        let matches = (0..total)
+            .filter(|_| {
+                let a = Bits::new(random());
+                let b = a ^ best.input;
+                weak_encrypt(a, &keys) ^ weak_encrypt(b, &keys) == best.output
+            })
+            .count();
+
+        // Expect about 1250 matches
+        let ratio = matches as f64 / total as f64;
+        assert!((ratio - best.probability).abs() < 0.01, "{ratio}");
+    }
+}
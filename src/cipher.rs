@@ -0,0 +1,249 @@
+//! A common interface for block ciphers, so analysis code can be written once and run on
+//! full DES, reduced-round DES or any other design that implements `BlockCipher`.
+use crate::bitslice;
+use crate::triple_des::TripleDes;
+use crate::{
+    decrypt_rounds, encrypt_rounds, generate_keys, weak_decrypt, weak_encrypt, Bits, Des,
+    RoundOptions, ROUNDS,
+};
+
+/// A block cipher on `N` bit blocks
+pub trait BlockCipher<const N: usize> {
+    /// Block size in bits
+    const BLOCK_SIZE: usize = N;
+
+    type Key;
+
+    fn new(key: Self::Key) -> Self;
+
+    fn encrypt_block(&self, plaintext: Bits<N>) -> Bits<N>;
+
+    fn decrypt_block(&self, ciphertext: Bits<N>) -> Bits<N>;
+
+    /// Encrypts every block of `plaintexts`. Ciphers that can do many blocks at once
+    /// faster than one by one, like the bitsliced DES variants, override this.
+    fn encrypt_many(&self, plaintexts: &[Bits<N>]) -> Vec<Bits<N>> {
+        plaintexts
+            .iter()
+            .map(|&plaintext| self.encrypt_block(plaintext))
+            .collect()
+    }
+}
+
+impl BlockCipher<64> for Des {
+    type Key = Bits<64>;
+
+    fn new(key: Bits<64>) -> Des {
+        Des::new(key)
+    }
+
+    fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        Des::encrypt_block(self, plaintext)
+    }
+
+    fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        Des::decrypt_block(self, ciphertext)
+    }
+}
+
+/// Reduced-round DES with the standard tables and without IP, the cipher the attacks in
+/// `linear` and `differential` are built for. They guess last round key bits through
+/// the DES S-boxes, E and P, so they can't run against other designs.
+pub trait ReducedRounds: BlockCipher<64> {
+    /// The key of the last round, the one the attacks recover bits of
+    fn last_round_key(&self) -> Bits<48>;
+}
+
+/// DES reduced to `R` rounds, without the initial and final permutations.
+/// Same as `weak_encrypt` and `weak_decrypt`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WeakDes<const R: usize> {
+    keys: [Bits<48>; R],
+}
+
+impl<const R: usize> WeakDes<R> {
+    /// Uses the given round keys, e.g. independent ones, instead of the key schedule.
+    pub fn from_round_keys(keys: [Bits<48>; R]) -> WeakDes<R> {
+        WeakDes { keys }
+    }
+
+    pub fn round_keys(&self) -> &[Bits<48>; R] {
+        &self.keys
+    }
+}
+
+impl<const R: usize> BlockCipher<64> for WeakDes<R> {
+    type Key = Bits<64>;
+
+    /// Takes the first `R` round keys of the DES key schedule
+    fn new(key: Bits<64>) -> WeakDes<R> {
+        assert!(R <= ROUNDS, "DES has only {ROUNDS} round keys.");
+        WeakDes {
+            keys: generate_keys(key),
+        }
+    }
+
+    fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        weak_encrypt(plaintext, &self.keys)
+    }
+
+    fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        weak_decrypt(ciphertext, &self.keys)
+    }
+
+    fn encrypt_many(&self, plaintexts: &[Bits<64>]) -> Vec<Bits<64>> {
+        bitslice::weak_encrypt_many(plaintexts, &self.keys)
+    }
+}
+
+/// Like `WeakDes`, but with the number of rounds chosen at runtime
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ReducedDes {
+    keys: Vec<Bits<48>>,
+}
+
+impl ReducedDes {
+    /// Uses the given round keys, one per round
+    pub fn from_round_keys(keys: Vec<Bits<48>>) -> ReducedDes {
+        assert!(!keys.is_empty());
+        ReducedDes { keys }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn round_keys(&self) -> &[Bits<48>] {
+        &self.keys
+    }
+}
+
+impl BlockCipher<64> for ReducedDes {
+    /// The DES key and the number of rounds
+    type Key = (Bits<64>, usize);
+
+    /// Takes the first `rounds` round keys of the DES key schedule
+    fn new((key, rounds): (Bits<64>, usize)) -> ReducedDes {
+        assert!(
+            (1..=ROUNDS).contains(&rounds),
+            "DES has only {ROUNDS} round keys."
+        );
+        let keys = generate_keys::<ROUNDS>(key);
+        ReducedDes::from_round_keys(keys[..rounds].to_vec())
+    }
+
+    fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        encrypt_rounds(
+            plaintext,
+            &self.keys,
+            1..=self.rounds(),
+            RoundOptions::default(),
+        )
+    }
+
+    fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        decrypt_rounds(
+            ciphertext,
+            &self.keys,
+            1..=self.rounds(),
+            RoundOptions::default(),
+        )
+    }
+
+    fn encrypt_many(&self, plaintexts: &[Bits<64>]) -> Vec<Bits<64>> {
+        bitslice::weak_encrypt_many(plaintexts, &self.keys)
+    }
+}
+
+impl<const R: usize> ReducedRounds for WeakDes<R> {
+    fn last_round_key(&self) -> Bits<48> {
+        self.keys[R - 1]
+    }
+}
+
+impl ReducedRounds for ReducedDes {
+    fn last_round_key(&self) -> Bits<48> {
+        self.keys[self.rounds() - 1]
+    }
+}
+
+impl BlockCipher<64> for TripleDes {
+    /// K1, K2 and K3
+    type Key = (Bits<64>, Bits<64>, Bits<64>);
+
+    fn new((k1, k2, k3): Self::Key) -> TripleDes {
+        TripleDes::new(k1, k2, k3)
+    }
+
+    fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        TripleDes::encrypt_block(self, plaintext)
+    }
+
+    fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        TripleDes::decrypt_block(self, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encrypt;
+    use rand::random;
+
+    fn roundtrip<C: BlockCipher<64>>(cipher: &C) {
+        for _ in 0..100 {
+            let plaintext = Bits::new(random());
+            assert_eq!(
+                cipher.decrypt_block(cipher.encrypt_block(plaintext)),
+                plaintext
+            );
+        }
+    }
+
+    fn many<C: BlockCipher<64>>(cipher: &C) {
+        let plaintexts: Vec<Bits<64>> = (0..100).map(|_| Bits::new(random())).collect();
+        let expected: Vec<Bits<64>> = plaintexts
+            .iter()
+            .map(|&plaintext| cipher.encrypt_block(plaintext))
+            .collect();
+
+        assert_eq!(cipher.encrypt_many(&plaintexts), expected);
+    }
+
+    #[test]
+    fn implementations() {
+        let key = Bits::new(random());
+        let plaintext = Bits::new(random());
+
+        let des = <Des as BlockCipher<64>>::new(key);
+        assert_eq!(
+            BlockCipher::encrypt_block(&des, plaintext),
+            encrypt(plaintext, key)
+        );
+        roundtrip(&des);
+        many(&des);
+
+        let weak = WeakDes::<5>::new(key);
+        assert_eq!(
+            weak.encrypt_block(plaintext),
+            weak_encrypt(plaintext, &generate_keys::<5>(key))
+        );
+        roundtrip(&weak);
+        many(&weak);
+
+        let reduced = ReducedDes::new((key, 5));
+        assert_eq!(reduced.round_keys(), weak.round_keys());
+        assert_eq!(
+            reduced.encrypt_block(plaintext),
+            weak.encrypt_block(plaintext)
+        );
+        roundtrip(&reduced);
+        many(&reduced);
+        assert_eq!(reduced.last_round_key(), weak.last_round_key());
+        assert_eq!(weak.last_round_key(), weak.round_keys()[4]);
+
+        let k2 = Bits::new(random());
+        roundtrip(&<TripleDes as BlockCipher<64>>::new((key, k2, key)));
+        assert_eq!(<WeakDes<3> as BlockCipher<64>>::BLOCK_SIZE, 64);
+    }
+}
@@ -0,0 +1,240 @@
+//! Difference distribution tables of the S-boxes, as used by Biham and Shamir.
+//!
+//! Entry (x', y') counts the inputs x for which S(x) ^ S(x ^ x') = y'. Input
+//! differences are 6 bit and output differences 4 bit, both written as integers with the
+//! most significant bit first like everywhere else in the crate.
+use crate::{sbox_lookup, SBOX};
+use std::fmt;
+
+/// An S-box entry that doesn't fit into 4 bits
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SboxEntryError {
+    /// 0-indexed like in `SBOX`
+    pub row: usize,
+    pub column: usize,
+    pub value: u8,
+}
+
+impl fmt::Display for SboxEntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "S-box entry {} in row {}, column {} has more than 4 bits",
+            self.value, self.row, self.column
+        )
+    }
+}
+
+impl std::error::Error for SboxEntryError {}
+
+/// Checks that every entry of an S-box fits into 4 bits
+pub(crate) fn check_entries(sbox: &[[u8; 16]; 4]) -> Result<(), SboxEntryError> {
+    for (row, entries) in sbox.iter().enumerate() {
+        if let Some(column) = entries.iter().position(|&value| value > 15) {
+            return Err(SboxEntryError {
+                row,
+                column,
+                value: entries[column],
+            });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ddt {
+    counts: [[u8; 16]; 64],
+}
+
+impl Ddt {
+    /// Computes the table of a single S-box in the layout of `SBOX`. The rows don't have
+    /// to be permutations like in DES, any entries up to 15 will do.
+    pub fn new(sbox: &[[u8; 16]; 4]) -> Result<Ddt, SboxEntryError> {
+        check_entries(sbox)?;
+        Ok(Ddt::build(sbox))
+    }
+
+    fn build(sbox: &[[u8; 16]; 4]) -> Ddt {
+        let mut counts = [[0; 16]; 64];
+
+        for (input, row) in counts.iter_mut().enumerate() {
+            for x in 0..64 {
+                let output = sbox_lookup(sbox, x) ^ sbox_lookup(sbox, x ^ input);
+                row[output as usize] += 1;
+            }
+        }
+
+        Ddt { counts }
+    }
+
+    /// The table of DES S-box `i`, 1-indexed like S1 to S8
+    pub fn des(i: usize) -> Ddt {
+        assert!((1..=8).contains(&i));
+        Ddt::build(&SBOX[i - 1])
+    }
+
+    /// Number of inputs (out of 64) for which `input` leads to `output`
+    pub fn count(&self, input: u8, output: u8) -> u8 {
+        self.counts[input as usize][output as usize]
+    }
+
+    pub fn probability(&self, input: u8, output: u8) -> f64 {
+        self.count(input, output) as f64 / 64.0
+    }
+
+    /// The most likely output difference for `input` and its count. Ties go to the
+    /// smallest output difference.
+    pub fn best_output(&self, input: u8) -> (u8, u8) {
+        let row = &self.counts[input as usize];
+        let mut best = 0;
+
+        for output in 1..16 {
+            if row[output] > row[best] {
+                best = output;
+            }
+        }
+
+        (best as u8, row[best])
+    }
+
+    /// Every possible output difference for `input` with its count, most likely first
+    pub fn outputs(&self, input: u8) -> Vec<(u8, u8)> {
+        let mut outputs: Vec<(u8, u8)> = (0..16)
+            .map(|output| (output, self.count(input, output)))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+
+        outputs.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        outputs
+    }
+
+    /// The table as CSV with a header row. Differences are written in hex.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("input");
+        for output in 0..16 {
+            csv += &format!(",{output:x}");
+        }
+        csv.push('\n');
+
+        for (input, row) in self.counts.iter().enumerate() {
+            csv += &format!("{input:02x}");
+            for count in row {
+                csv += &format!(",{count}");
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+/// Prints the table in the layout of the Biham–Shamir paper
+impl fmt::Display for Ddt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "   ")?;
+        for output in 0..16 {
+            write!(f, " {output:>2X}")?;
+        }
+        writeln!(f)?;
+
+        for (input, row) in self.counts.iter().enumerate() {
+            write!(f, "{input:02X} ")?;
+            for count in row {
+                write!(f, " {count:>2}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rows_sum_to_64() {
+        for i in 1..=8 {
+            let ddt = Ddt::des(i);
+
+            assert_eq!(ddt.count(0, 0), 64);
+            for input in 0..64 {
+                let total: u32 = (0..16).map(|output| ddt.count(input, output) as u32).sum();
+                assert_eq!(total, 64);
+            }
+        }
+    }
+
+    // Values from Biham and Shamir, "Differential Cryptanalysis of DES-like Cryptosystems"
+    #[test]
+    fn published_entries() {
+        let s1 = Ddt::des(1);
+
+        let row_01 = [0, 0, 0, 6, 0, 2, 4, 4, 0, 10, 12, 4, 10, 6, 2, 4];
+        let row_34 = [0, 8, 16, 6, 2, 0, 0, 12, 6, 0, 0, 0, 0, 8, 0, 6];
+        for output in 0..16 {
+            assert_eq!(s1.count(0x01, output), row_01[output as usize]);
+            assert_eq!(s1.count(0x34, output), row_34[output as usize]);
+        }
+        assert_eq!(s1.best_output(0x34), (0x2, 16));
+
+        // The S-boxes of the 2 round iterative characteristic 19600000 -> 0
+        assert_eq!(s1.count(0x03, 0), 14);
+        assert_eq!(Ddt::des(2).count(0x32, 0), 8);
+        assert_eq!(Ddt::des(3).count(0x2c, 0), 10);
+
+        // No S-box maps a non-zero difference to one with probability above 1/4
+        for i in 1..=8 {
+            let ddt = Ddt::des(i);
+            assert!((1..64).all(|input| ddt.best_output(input).1 <= 16));
+        }
+    }
+
+    #[test]
+    fn queries() {
+        let s1 = Ddt::des(1);
+
+        let outputs = s1.outputs(0x34);
+        assert_eq!(outputs[0], (0x2, 16));
+        assert_eq!(outputs.len(), 8);
+        assert_eq!(s1.probability(0x34, 0x2), 0.25);
+
+        let csv = s1.to_csv();
+        assert_eq!(csv.lines().count(), 65);
+        assert_eq!(
+            csv.lines().nth(0x35).unwrap(),
+            "34,0,8,16,6,2,0,0,12,6,0,0,0,0,8,0,6"
+        );
+
+        let pretty = s1.to_string();
+        assert!(pretty.lines().nth(1).unwrap().starts_with("00  64  0"));
+    }
+
+    #[test]
+    fn custom_sbox() {
+        // The identity on the inner bits has a trivial table
+        let mut sbox = [[0; 16]; 4];
+        for row in sbox.iter_mut() {
+            *row = std::array::from_fn(|i| i as u8);
+        }
+        let ddt = Ddt::new(&sbox).unwrap();
+
+        assert_eq!(ddt.best_output(0b011110), (0xf, 64));
+        assert_eq!(ddt.outputs(0b100001), vec![(0, 64)]);
+
+        // Rows don't have to be permutations
+        let constant = Ddt::new(&[[7; 16]; 4]).unwrap();
+        assert_eq!(constant.best_output(0b101010), (0, 64));
+
+        sbox[2][5] = 16;
+        assert_eq!(
+            Ddt::new(&sbox),
+            Err(SboxEntryError {
+                row: 2,
+                column: 5,
+                value: 16
+            })
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{generate_keys, sp, Bits, IP_INVERSE_LUT, IP_LUT, ROUNDS};
+use rayon::prelude::*;
+
+/// Blocks per rayon task, so small blocks don't drown in scheduling overhead
+const PARALLEL_CHUNK: usize = 1024;
+
+/// DES with the key schedule computed once up front. Use this instead of `encrypt` and
+/// `decrypt` when encrypting more than one block under the same key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Des {
+    keys: [Bits<48>; ROUNDS],
+}
+
+impl Des {
+    pub fn new(key: Bits<64>) -> Des {
+        Des {
+            keys: generate_keys(key),
+        }
+    }
+
+    /// Uses the given round keys instead of deriving them from a master key.
+    pub fn from_round_keys(keys: [Bits<48>; ROUNDS]) -> Des {
+        Des { keys }
+    }
+
+    pub fn round_keys(&self) -> &[Bits<48>; ROUNDS] {
+        &self.keys
+    }
+
+    pub fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        IP_INVERSE_LUT.apply(sp::weak_encrypt(IP_LUT.apply(plaintext), &self.keys))
+    }
+
+    pub fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        IP_INVERSE_LUT.apply(sp::weak_decrypt(IP_LUT.apply(ciphertext), &self.keys))
+    }
+
+    /// Encrypts every block in place
+    pub fn encrypt_blocks(&self, blocks: &mut [Bits<64>]) {
+        for block in blocks {
+            *block = self.encrypt_block(*block);
+        }
+    }
+
+    /// Decrypts every block in place
+    pub fn decrypt_blocks(&self, blocks: &mut [Bits<64>]) {
+        for block in blocks {
+            *block = self.decrypt_block(*block);
+        }
+    }
+
+    /// Same as `encrypt_blocks`, but spread over the rayon thread pool
+    pub fn par_encrypt_blocks(&self, blocks: &mut [Bits<64>]) {
+        blocks
+            .par_chunks_mut(PARALLEL_CHUNK)
+            .for_each(|chunk| self.encrypt_blocks(chunk));
+    }
+
+    /// Same as `decrypt_blocks`, but spread over the rayon thread pool
+    pub fn par_decrypt_blocks(&self, blocks: &mut [Bits<64>]) {
+        blocks
+            .par_chunks_mut(PARALLEL_CHUNK)
+            .for_each(|chunk| self.decrypt_blocks(chunk));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decrypt, encrypt};
+    use rand::random;
+
+    #[test]
+    fn test_vector() {
+        let des = Des::new(Bits::new(0x0123456789abcdef));
+        let plaintext = Bits::new(0x4e6f772069732074);
+
+        let ciphertext = des.encrypt_block(plaintext);
+        assert_eq!(ciphertext.as_u64(), 0x3fa40e8a984d4815);
+        assert_eq!(des.decrypt_block(ciphertext), plaintext);
+    }
+
+    #[test]
+    fn blocks_match_single_calls() {
+        let key = Bits::new(random());
+        let des = Des::new(key);
+
+        let plaintexts: Vec<Bits<64>> = (0..5000).map(|_| Bits::new(random())).collect();
+        let expected: Vec<_> = plaintexts.iter().map(|&p| encrypt(p, key)).collect();
+
+        let mut blocks = plaintexts.clone();
+        des.encrypt_blocks(&mut blocks);
+        assert_eq!(blocks, expected);
+        des.decrypt_blocks(&mut blocks);
+        assert_eq!(blocks, plaintexts);
+
+        let mut blocks = plaintexts.clone();
+        des.par_encrypt_blocks(&mut blocks);
+        assert_eq!(blocks, expected);
+        des.par_decrypt_blocks(&mut blocks);
+        assert_eq!(blocks, plaintexts);
+
+        assert_eq!(des.decrypt_block(expected[0]), decrypt(expected[0], key));
+    }
+}
@@ -0,0 +1,364 @@
+//! Biham and Shamir's differential attack against reduced-round DES.
+//!
+//! A characteristic over the first r - 1 rounds predicts both halves going into the last
+//! round. For a right pair, the right half of the ciphertext difference is the predicted
+//! input difference of the last round function, and its output difference is the left
+//! half of the ciphertext difference xored with the predicted L_(r-1) difference. Pairs
+//! that don't fit this (or whose S-box differences are impossible) are thrown away. Every
+//! remaining pair votes for the 6 key bits of each active last round S-box that turn its
+//! inputs into the expected output difference. The right key bits get a vote from every
+//! right pair, wrong ones only now and then.
+//!
+//! Everything happens in the domain of `weak_encrypt`, without IP.
+use crate::characteristic::{self, Characteristic};
+use crate::cipher::ReducedRounds;
+use crate::trail::sbox_output_masks;
+use crate::{fast_expand, sbox_lookup, Bits, SBOX};
+use rand::random;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+
+/// Pairs per batch handed to `BlockCipher::encrypt_many`
+const BATCH: usize = 1 << 12;
+
+/// Characteristics over more rounds than this take minutes to search for, so longer ones
+/// are built by iterating a 2 round characteristic.
+pub const MAX_SEARCH_ROUNDS: usize = 5;
+
+/// Up to `k` likely characteristics over `rounds` rounds (at least 2), most likely first.
+/// Up to `MAX_SEARCH_ROUNDS` rounds they come from `characteristic::search`, beyond that
+/// they are iterated 2 round characteristics, which are good but not always the best.
+pub fn characteristics(rounds: usize, k: usize) -> Vec<Characteristic> {
+    if rounds <= MAX_SEARCH_ROUNDS {
+        characteristic::search(rounds, k)
+    } else {
+        characteristic::search_iterative(2, k)
+            .iter()
+            .map(|c| c.iterate(rounds))
+            .collect()
+    }
+}
+
+/// A guess for the key bits of one S-box and how many pairs voted for it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyCandidate {
+    pub key: u8,
+    pub count: usize,
+}
+
+/// The ranked guesses for one active S-box of the last round
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SboxCandidates {
+    /// 1-indexed like S1 to S8
+    pub sbox: usize,
+    /// Input difference of the S-box. The key bits k and k ^ input swap the inputs of
+    /// every pair, so they always get the same number of votes and can't be told apart.
+    pub input: u8,
+    /// All 64 guesses, most votes first
+    pub candidates: Vec<KeyCandidate>,
+}
+
+impl SboxCandidates {
+    /// Position of `key` in the ranking, 0 being the best guess
+    pub fn rank(&self, key: u8) -> usize {
+        self.candidates
+            .iter()
+            .position(|candidate| candidate.key == key)
+            .unwrap()
+    }
+}
+
+/// The outcome of running an attack
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Recovery {
+    /// Number of chosen plaintext pairs encrypted
+    pub pairs: usize,
+    /// Number of pairs that survived the filter. Wrong pairs rarely get through, so
+    /// this is close to the number of right pairs, but it may include some.
+    pub surviving_pairs: usize,
+    pub sboxes: Vec<SboxCandidates>,
+}
+
+impl Recovery {
+    /// The best guess for the last round key, with every bit of the inactive S-boxes
+    /// set to 0, and the mask of the recovered bits. See `SboxCandidates::input` for why
+    /// the bits of an S-box may be off by its input difference.
+    pub fn best_key(&self) -> (Bits<48>, Bits<48>) {
+        let mut key = 0;
+        let mut mask = 0;
+
+        for sbox in &self.sboxes {
+            let shift = 42 - 6 * (sbox.sbox - 1);
+            key |= (sbox.candidates[0].key as u64) << shift;
+            mask |= 0x3f << shift;
+        }
+
+        (Bits::new(key), Bits::new(mask))
+    }
+
+    /// The rank of the key bits of `round_key` for every active S-box
+    pub fn ranks(&self, round_key: Bits<48>) -> Vec<usize> {
+        let round_key = round_key.as_u64();
+
+        self.sboxes
+            .iter()
+            .map(|sbox| {
+                let key = round_key >> (42 - 6 * (sbox.sbox - 1)) & 0x3f;
+                sbox.rank(key as u8)
+            })
+            .collect()
+    }
+}
+
+pub struct DifferentialAttack {
+    characteristic: Characteristic,
+    /// See `Characteristic::worst_probability`
+    worst_probability: f64,
+    /// Predicted difference of R_(r-1), the input of the last round function
+    right: u32,
+    /// Predicted difference of L_(r-1)
+    left: u32,
+    /// Input difference of every S-box of the last round, 0 for the inactive ones
+    sbox_inputs: [u8; 8],
+}
+
+impl DifferentialAttack {
+    /// Attacks `characteristic.rounds.len() + 1` rounds. Panics if the characteristic
+    /// leaves every S-box of the last round inactive.
+    pub fn new(characteristic: Characteristic) -> DifferentialAttack {
+        let (right, left) = characteristic.output.split::<32>();
+        let expanded = fast_expand(right).as_u64();
+        let sbox_inputs = std::array::from_fn(|j| (expanded >> (42 - 6 * j) & 0x3f) as u8);
+
+        assert!(
+            sbox_inputs.iter().any(|&input| input != 0),
+            "The characteristic has to activate an S-box in the last round."
+        );
+
+        DifferentialAttack {
+            worst_probability: characteristic.worst_probability(),
+            characteristic,
+            right: right.as_u64() as u32,
+            left: left.as_u64() as u32,
+            sbox_inputs,
+        }
+    }
+
+    /// Picks the characteristic over `rounds - 1` rounds (out of `characteristics`) that
+    /// activates an S-box in the last round and is the most likely under the least
+    /// favourable key. The most likely one on average can be much worse for some keys, like
+    /// Biham and Shamir's 19600000, which some keys make 2.5 times less likely per round.
+    pub fn for_rounds(rounds: usize) -> Option<DifferentialAttack> {
+        assert!(rounds >= 3);
+
+        characteristics(rounds - 1, 10)
+            .into_iter()
+            .filter(|c| c.output.split::<32>().0.as_u64() != 0)
+            .map(DifferentialAttack::new)
+            .reduce(|best, attack| {
+                if attack.worst_probability > best.worst_probability {
+                    attack
+                } else {
+                    best
+                }
+            })
+    }
+
+    pub fn characteristic(&self) -> &Characteristic {
+        &self.characteristic
+    }
+
+    /// The active S-boxes of the last round, 1-indexed like S1 to S8
+    pub fn active_sboxes(&self) -> Vec<usize> {
+        (1..=8).filter(|&i| self.sbox_inputs[i - 1] != 0).collect()
+    }
+
+    /// Enough pairs to expect at least 8 right pairs whatever the key is
+    pub fn pairs_needed(&self) -> usize {
+        (8.0 / self.worst_probability).ceil() as usize
+    }
+
+    /// The key bits each S-box suggests for the pair of ciphertexts `a` and `b`, if the
+    /// pair can be a right pair. Inactive S-boxes get no suggestions.
+    fn suggestions(&self, a: Bits<64>, b: Bits<64>) -> Option<[Vec<u8>; 8]> {
+        let (r_a, l_a) = a.split::<32>();
+        let (r_b, l_b) = b.split::<32>();
+
+        // L_r = R_(r-1)
+        if (l_a ^ l_b).as_u64() as u32 != self.right {
+            return None;
+        }
+
+        // P^-1 splits differences into S-boxes just like masks
+        let output = (r_a ^ r_b).as_u64() as u32 ^ self.left;
+        let outputs = sbox_output_masks(output);
+
+        let expanded_a = fast_expand(l_a).as_u64();
+        let expanded_b = fast_expand(l_b).as_u64();
+
+        let mut suggestions: [Vec<u8>; 8] = Default::default();
+        for j in 0..8 {
+            if self.sbox_inputs[j] == 0 {
+                if outputs[j] != 0 {
+                    return None;
+                }
+                continue;
+            }
+
+            let x_a = (expanded_a >> (42 - 6 * j) & 0x3f) as usize;
+            let x_b = (expanded_b >> (42 - 6 * j) & 0x3f) as usize;
+
+            suggestions[j] = (0..64u8)
+                .filter(|&key| {
+                    let key = key as usize;
+                    sbox_lookup(&SBOX[j], x_a ^ key) ^ sbox_lookup(&SBOX[j], x_b ^ key)
+                        == outputs[j]
+                })
+                .collect();
+
+            if suggestions[j].is_empty() {
+                return None;
+            }
+        }
+
+        Some(suggestions)
+    }
+
+    /// Encrypts `pairs` random chosen plaintext pairs with the input difference of the
+    /// characteristic under `cipher` and counts the votes for the last round key bits.
+    pub fn run<C>(&self, cipher: &C, pairs: usize) -> Recovery
+    where
+        C: ReducedRounds + Sync,
+    {
+        let (surviving_pairs, counts) = (0..pairs.div_ceil(BATCH))
+            .into_par_iter()
+            .map(|batch| {
+                let count = BATCH.min(pairs - batch * BATCH);
+                let plaintexts: Vec<Bits<64>> = (0..count).map(|_| Bits::new(random())).collect();
+                let partners: Vec<Bits<64>> = plaintexts
+                    .iter()
+                    .map(|&plaintext| plaintext ^ self.characteristic.input)
+                    .collect();
+
+                let ciphertexts_a = cipher.encrypt_many(&plaintexts);
+                let ciphertexts_b = cipher.encrypt_many(&partners);
+
+                let mut surviving_pairs = 0;
+                let mut counts = [[0usize; 64]; 8];
+                for (&a, &b) in ciphertexts_a.iter().zip(&ciphertexts_b) {
+                    if let Some(suggestions) = self.suggestions(a, b) {
+                        surviving_pairs += 1;
+                        for (count, keys) in counts.iter_mut().zip(suggestions) {
+                            for key in keys {
+                                count[key as usize] += 1;
+                            }
+                        }
+                    }
+                }
+
+                (surviving_pairs, counts)
+            })
+            .reduce(
+                || (0, [[0; 64]; 8]),
+                |(right_a, mut counts_a), (right_b, counts_b)| {
+                    for (a, b) in counts_a.iter_mut().zip(counts_b) {
+                        for (a, b) in a.iter_mut().zip(b) {
+                            *a += b;
+                        }
+                    }
+                    (right_a + right_b, counts_a)
+                },
+            );
+
+        let sboxes = self
+            .active_sboxes()
+            .into_iter()
+            .map(|sbox| {
+                let mut candidates: Vec<KeyCandidate> = counts[sbox - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(key, &count)| KeyCandidate {
+                        key: key as u8,
+                        count,
+                    })
+                    .collect();
+
+                candidates.sort_by_key(|candidate| Reverse(candidate.count));
+                SboxCandidates {
+                    sbox,
+                    input: self.sbox_inputs[sbox - 1],
+                    candidates,
+                }
+            })
+            .collect();
+
+        Recovery {
+            pairs,
+            surviving_pairs,
+            sboxes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher::{BlockCipher, WeakDes};
+
+    fn attack<const R: usize>(key: Bits<64>) {
+        let attack = DifferentialAttack::for_rounds(R).unwrap();
+        let cipher = WeakDes::<R>::new(key);
+
+        let recovery = attack.run(&cipher, attack.pairs_needed());
+        assert_eq!(recovery.pairs, attack.pairs_needed());
+        assert!(recovery.surviving_pairs > 0);
+
+        // Wrong pairs practically never get through the filter, and right pairs vote for
+        // the right key bits every time, so they are at the top (maybe tied with others)
+        let round_key = cipher.last_round_key();
+        for (sbox, rank) in recovery.sboxes.iter().zip(recovery.ranks(round_key)) {
+            assert_eq!(sbox.candidates[rank].count, recovery.surviving_pairs);
+            assert_eq!(sbox.candidates[0].count, recovery.surviving_pairs);
+        }
+    }
+
+    #[test]
+    fn four_rounds() {
+        attack::<4>(Bits::new(random()));
+    }
+
+    #[test]
+    #[ignore = "takes a while without optimizations"]
+    fn six_rounds() {
+        attack::<6>(Bits::new(random()));
+    }
+
+    #[test]
+    #[ignore = "takes a while without optimizations"]
+    fn eight_rounds() {
+        attack::<8>(Bits::new(random()));
+    }
+
+    #[test]
+    fn recovered_bits() {
+        let attack = DifferentialAttack::for_rounds(4).unwrap();
+        let cipher = WeakDes::<4>::new(Bits::new(random()));
+        let round_key = cipher.last_round_key();
+
+        let recovery = attack.run(&cipher, 4 * attack.pairs_needed());
+        let (key, mask) = recovery.best_key();
+        assert_eq!(recovery.sboxes.len(), attack.active_sboxes().len());
+
+        let wrong = key.as_u64() ^ round_key.as_u64() & mask.as_u64();
+        for sbox in &recovery.sboxes {
+            let wrong = (wrong >> (42 - 6 * (sbox.sbox - 1)) & 0x3f) as u8;
+            assert!(wrong == 0 || wrong == sbox.input);
+
+            let key = sbox.candidates[0].key;
+            assert_eq!(
+                sbox.candidates[0].count,
+                sbox.candidates[sbox.rank(key ^ sbox.input)].count
+            );
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Key handling. DES keys are 64 bits, but bits 8, 16, ..., 64 are parity bits that
+//! `PC1` drops. Every byte of a proper key has an odd amount of ones.
+use crate::{Bits, PC1};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParityError {
+    /// Bytes with wrong parity, the leftmost byte being the most significant bit.
+    pub bad_bytes: u8,
+}
+
+impl fmt::Display for ParityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wrong parity in key bytes")?;
+        for i in 0..8 {
+            if self.bad_bytes & (0x80 >> i) != 0 {
+                write!(f, " {}", i + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParityError {}
+
+/// Returns the key with every parity bit set so each byte has odd parity.
+pub fn set_parity(key: Bits<64>) -> Bits<64> {
+    let mut bytes = key.as_u64().to_be_bytes();
+
+    for byte in bytes.iter_mut() {
+        // Clear the parity bit, and set it again if the other 7 bits are even
+        *byte &= 0xfe;
+        *byte |= (byte.count_ones() & 1 == 0) as u8;
+    }
+
+    Bits::new(u64::from_be_bytes(bytes))
+}
+
+/// Checks that every byte of the key has odd parity.
+pub fn check_parity(key: Bits<64>) -> Result<(), ParityError> {
+    let bad_bytes = key
+        .as_u64()
+        .to_be_bytes()
+        .iter()
+        .fold(0, |bad, byte| bad << 1 | (byte.count_ones() & 1 == 0) as u8);
+
+    if bad_bytes == 0 {
+        Ok(())
+    } else {
+        Err(ParityError { bad_bytes })
+    }
+}
+
+/// Strict key constructor. Rejects keys that don't have odd parity.
+pub fn strict_key(key: u64) -> Result<Bits<64>, ParityError> {
+    let key = Bits::new(key);
+    check_parity(key)?;
+    Ok(key)
+}
+
+/// Inverse of `PC1`. Builds a key with proper parity from the 56 bit C and D registers.
+pub(crate) fn key_from_cd(cd: Bits<56>) -> Bits<64> {
+    let mut key = Bits::<64>::new(0);
+
+    for (i, j) in PC1.iter().enumerate() {
+        key.set(*j as usize, cd.get(i + 1));
+    }
+
+    set_parity(key)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyClass {
+    /// All 16 round keys are equal, so encryption is an involution.
+    Weak,
+    /// The round keys alternate between two values. Encrypting with `dual` undoes
+    /// encrypting with this key.
+    SemiWeak {
+        dual: Bits<64>,
+    },
+    /// The key schedule only produces 4 different round keys.
+    PossiblyWeak,
+    Normal,
+}
+
+const HALF_MASK: u64 = (1 << 28) - 1;
+
+/// C or D register consisting of a single repeated bit.
+fn is_constant(half: Bits<28>) -> bool {
+    half.as_u64() == 0 || half.as_u64() == HALF_MASK
+}
+
+/// C or D register with a period of at most 2, like 0101...
+fn has_period_two(half: Bits<28>) -> bool {
+    half.rotate_left(2) == half
+}
+
+/// C or D register with a period of at most 2, or a rotation of 0011...
+fn is_regular(half: Bits<28>) -> bool {
+    has_period_two(half) || half.rotate_left(2).as_u64() == half.as_u64() ^ HALF_MASK
+}
+
+/// Classifies a key as weak, semi-weak or possibly weak. This happens when both
+/// halves of the key schedule are (close to) periodic, because the rotations of
+/// `generate_keys` then keep producing the same round keys.
+///
+/// The 4 weak, 12 semi-weak and 48 possibly weak keys are the ones usually listed in
+/// the literature. Keys whose halves are rotations of 0001... or 0111... also only give
+/// 4 round keys, but aren't part of that list and are classified as `Normal`.
+pub fn classify(key: Bits<64>) -> KeyClass {
+    let (c, d) = key.permute(&PC1).split::<28>();
+
+    if is_constant(c) && is_constant(d) {
+        KeyClass::Weak
+    } else if has_period_two(c) && has_period_two(d) {
+        // Rotating by one swaps 0101... and 1010..., which reverses the round keys
+        let dual = key_from_cd(c.rotate_left(1).concat(d.rotate_left(1)));
+        KeyClass::SemiWeak { dual }
+    } else if is_regular(c) && is_regular(d) {
+        KeyClass::PossiblyWeak
+    } else {
+        KeyClass::Normal
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{encrypt, generate_keys};
+    use std::collections::HashSet;
+
+    /// Every key whose C and D registers are 4-periodic
+    fn periodic_keys() -> Vec<Bits<64>> {
+        let mut keys = Vec::new();
+
+        for c in 0..16 {
+            for d in 0..16 {
+                let repeat = |pattern: u64| (0..7).fold(0, |acc, _| acc << 4 | pattern);
+                let cd: Bits<56> = Bits::new(repeat(c) << 28 | repeat(d));
+                keys.push(key_from_cd(cd));
+            }
+        }
+
+        keys
+    }
+
+    #[test]
+    fn known_keys() {
+        // The FIPS 81 key and the usual weak keys have proper parity
+        assert_eq!(check_parity(Bits::new(0x0123456789abcdef)), Ok(()));
+        assert_eq!(check_parity(Bits::new(0x0101010101010101)), Ok(()));
+        assert_eq!(check_parity(Bits::new(0xfefefefefefefefe)), Ok(()));
+
+        assert_eq!(
+            check_parity(Bits::new(0)),
+            Err(ParityError { bad_bytes: 0xff })
+        );
+        assert_eq!(
+            check_parity(Bits::new(0x0023456789abcdee)),
+            Err(ParityError {
+                bad_bytes: 0b1000_0001
+            })
+        );
+    }
+
+    #[test]
+    fn set_parity_fixes_key() {
+        assert_eq!(set_parity(Bits::new(0)).as_u64(), 0x0101010101010101);
+        assert_eq!(
+            set_parity(Bits::new(0x0023456789abcdee)).as_u64(),
+            0x0123456789abcdef
+        );
+
+        for key in [0xdeadbeefcafebabe, 0x133457799bbcdff1, u64::MAX] {
+            let fixed = set_parity(Bits::new(key));
+            assert_eq!(check_parity(fixed), Ok(()));
+
+            // Only the parity bits are changed, so the key stays the same
+            assert_eq!(
+                fixed.as_u64() & !0x0101010101010101,
+                key & !0x0101010101010101
+            );
+            let plaintext = Bits::new(0x4e6f772069732074);
+            assert_eq!(
+                encrypt(plaintext, fixed),
+                encrypt(plaintext, Bits::new(key))
+            );
+        }
+    }
+
+    #[test]
+    fn key_from_cd_inverts_pc1() {
+        let key = Bits::new(0x133457799bbcdff1);
+        assert_eq!(key_from_cd(key.permute(&PC1)), key);
+    }
+
+    #[test]
+    fn weak_key_counts() {
+        let mut weak = 0;
+        let mut semi_weak = 0;
+        let mut possibly_weak = 0;
+
+        for key in periodic_keys() {
+            let distinct: HashSet<_> = generate_keys::<16>(key).into_iter().collect();
+
+            match classify(key) {
+                KeyClass::Weak => {
+                    weak += 1;
+                    assert_eq!(distinct.len(), 1);
+                }
+                KeyClass::SemiWeak { .. } => {
+                    semi_weak += 1;
+                    assert_eq!(distinct.len(), 2);
+                }
+                KeyClass::PossiblyWeak => {
+                    possibly_weak += 1;
+                    assert_eq!(distinct.len(), 4);
+                }
+                KeyClass::Normal => {}
+            }
+        }
+
+        assert_eq!((weak, semi_weak, possibly_weak), (4, 12, 48));
+    }
+
+    #[test]
+    fn weak_keys_are_involutions() {
+        let plaintext = Bits::new(0x4e6f772069732074);
+
+        for key in [
+            0x0101010101010101,
+            0xfefefefefefefefe,
+            0xe0e0e0e0f1f1f1f1,
+            0x1f1f1f1f0e0e0e0e,
+        ] {
+            let key = Bits::new(key);
+            assert_eq!(classify(key), KeyClass::Weak);
+            assert_eq!(encrypt(encrypt(plaintext, key), key), plaintext);
+        }
+    }
+
+    #[test]
+    fn semi_weak_duals() {
+        let plaintext = Bits::new(0x4e6f772069732074);
+
+        let pairs = [
+            (0x01fe01fe01fe01fe, 0xfe01fe01fe01fe01),
+            (0x1fe01fe00ef10ef1, 0xe01fe01ff10ef10e),
+            (0x01e001e001f101f1, 0xe001e001f101f101),
+        ];
+
+        for (a, b) in pairs {
+            let (a, b) = (Bits::new(a), Bits::new(b));
+            assert_eq!(classify(a), KeyClass::SemiWeak { dual: b });
+            assert_eq!(classify(b), KeyClass::SemiWeak { dual: a });
+            assert_eq!(encrypt(encrypt(plaintext, a), b), plaintext);
+        }
+
+        assert_eq!(
+            classify(Bits::new(0x1f1f01010e0e0101)),
+            KeyClass::PossiblyWeak
+        );
+        assert_eq!(classify(Bits::new(0x0123456789abcdef)), KeyClass::Normal);
+    }
+
+    #[test]
+    fn strict() {
+        assert!(strict_key(0x133457799bbcdff1).is_ok());
+        assert_eq!(
+            strict_key(0x133457799bbcdff0),
+            Err(ParityError { bad_bytes: 0x01 })
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Linear approximation tables of the S-boxes, as used by Matsui.
+//!
+//! Entry (a, b) is the number of inputs x for which a·x = b·S(x), minus 32. So it is a
+//! signed count where 0 means no bias and ±32 a linear relation that always holds. The
+//! input mask a is 6 bit and the output mask b 4 bit, both most significant bit first
+//! like `Ddt`.
+use crate::ddt::{check_entries, SboxEntryError};
+use crate::{sbox_lookup, Bits, SBOX};
+use std::cmp::Reverse;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lat {
+    biases: [[i8; 16]; 64],
+}
+
+impl Lat {
+    /// Computes the table of a single S-box in the layout of `SBOX`. Checked like
+    /// `Ddt::new`.
+    pub fn new(sbox: &[[u8; 16]; 4]) -> Result<Lat, SboxEntryError> {
+        check_entries(sbox)?;
+        Ok(Lat::build(sbox))
+    }
+
+    fn build(sbox: &[[u8; 16]; 4]) -> Lat {
+        let mut biases = [[0; 16]; 64];
+
+        for (input_mask, row) in biases.iter_mut().enumerate() {
+            let input_mask = Bits::<6>::new(input_mask as u64);
+
+            for (output_mask, bias) in row.iter_mut().enumerate() {
+                let output_mask = Bits::<4>::new(output_mask as u64);
+
+                let matches = (0..64)
+                    .filter(|&x| {
+                        let output = Bits::new(sbox_lookup(sbox, x) as u64);
+                        input_mask.dot_product(Bits::new(x as u64))
+                            == output_mask.dot_product(output)
+                    })
+                    .count();
+
+                *bias = matches as i8 - 32;
+            }
+        }
+
+        Lat { biases }
+    }
+
+    /// The table of DES S-box `i`, 1-indexed like S1 to S8
+    pub fn des(i: usize) -> Lat {
+        assert!((1..=8).contains(&i));
+        Lat::build(&SBOX[i - 1])
+    }
+
+    /// Number of inputs for which the approximation holds, minus 32
+    pub fn bias(&self, input_mask: u8, output_mask: u8) -> i8 {
+        self.biases[input_mask as usize][output_mask as usize]
+    }
+
+    /// Probability that the approximation holds
+    pub fn probability(&self, input_mask: u8, output_mask: u8) -> f64 {
+        (32 + self.bias(input_mask, output_mask) as i32) as f64 / 64.0
+    }
+
+    /// The approximation with non-zero masks and the largest absolute bias, as
+    /// `(input_mask, output_mask, bias)`. Ties go to the smallest masks.
+    pub fn best(&self) -> (u8, u8, i8) {
+        let mut best = (0, 0, 0i8);
+
+        for input_mask in 1..64 {
+            for output_mask in 1..16 {
+                let bias = self.bias(input_mask, output_mask);
+                if bias.abs() > best.2.abs() {
+                    best = (input_mask, output_mask, bias);
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Every biased output mask for `input_mask`, largest absolute bias first
+    pub fn approximations(&self, input_mask: u8) -> Vec<(u8, i8)> {
+        let mut approximations: Vec<(u8, i8)> = (0..16)
+            .map(|output_mask| (output_mask, self.bias(input_mask, output_mask)))
+            .filter(|&(_, bias)| bias != 0)
+            .collect();
+
+        approximations.sort_by_key(|&(_, bias)| Reverse(bias.abs()));
+        approximations
+    }
+
+    /// The table as CSV with a header row. Masks are written in hex.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("input");
+        for output_mask in 0..16 {
+            csv += &format!(",{output_mask:x}");
+        }
+        csv.push('\n');
+
+        for (input_mask, row) in self.biases.iter().enumerate() {
+            csv += &format!("{input_mask:02x}");
+            for bias in row {
+                csv += &format!(",{bias}");
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+impl fmt::Display for Lat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "   ")?;
+        for output_mask in 0..16 {
+            write!(f, " {output_mask:>3X}")?;
+        }
+        writeln!(f)?;
+
+        for (input_mask, row) in self.biases.iter().enumerate() {
+            write!(f, "{input_mask:02X} ")?;
+            for bias in row {
+                write!(f, " {bias:>3}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The most biased approximation over all DES S-boxes as `(sbox, input_mask,
+/// output_mask, bias)`. This is Matsui's approximation of S5, 0x10 -> 0xf with a bias
+/// of -20, which his attack is built around.
+pub fn best_des_approximation() -> (usize, u8, u8, i8) {
+    let mut best = (0, 0, 0, 0i8);
+
+    for i in 1..=8 {
+        let (input_mask, output_mask, bias) = Lat::des(i).best();
+        if bias.abs() > best.3.abs() {
+            best = (i, input_mask, output_mask, bias);
+        }
+    }
+
+    best
+}
+
+/// The Boolean function x -> mask·S(x) as a truth table indexed by x
+pub fn component(sbox: &[[u8; 16]; 4], output_mask: u8) -> [bool; 64] {
+    let output_mask = Bits::<4>::new(output_mask as u64);
+    std::array::from_fn(|x| output_mask.dot_product(Bits::new(sbox_lookup(sbox, x) as u64)))
+}
+
+/// Fast Walsh–Hadamard transform of a Boolean function given as a truth table, whose
+/// length has to be a power of two. Entry a is the sum over x of (-1)^(f(x) ^ a·x).
+pub fn walsh_hadamard(truth_table: &[bool]) -> Vec<i32> {
+    assert!(truth_table.len().is_power_of_two());
+
+    let mut spectrum: Vec<i32> = truth_table
+        .iter()
+        .map(|&bit| if bit { -1 } else { 1 })
+        .collect();
+
+    let mut width = 1;
+    while width < spectrum.len() {
+        for block in spectrum.chunks_mut(2 * width) {
+            let (low, high) = block.split_at_mut(width);
+            for (a, b) in low.iter_mut().zip(high) {
+                (*a, *b) = (*a + *b, *a - *b);
+            }
+        }
+        width *= 2;
+    }
+
+    spectrum
+}
+
+/// Walsh spectrum of the component function for `output_mask`. Entry a is twice the
+/// LAT entry (a, output_mask).
+pub fn walsh_spectrum(sbox: &[[u8; 16]; 4], output_mask: u8) -> Vec<i32> {
+    walsh_hadamard(&component(sbox, output_mask))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Matsui, "Linear Cryptanalysis Method for DES Cipher"
+    #[test]
+    fn s5_approximation() {
+        let s5 = Lat::des(5);
+
+        assert_eq!(s5.bias(0x10, 0xf), -20);
+        assert_eq!(s5.probability(0x10, 0xf), 12.0 / 64.0);
+        assert_eq!(s5.best(), (0x10, 0xf, -20));
+        assert_eq!(s5.approximations(0x10)[0], (0xf, -20));
+
+        assert_eq!(best_des_approximation(), (5, 0x10, 0xf, -20));
+    }
+
+    #[test]
+    fn table_properties() {
+        for i in 1..=8 {
+            let lat = Lat::des(i);
+
+            assert_eq!(lat.bias(0, 0), 32);
+            for mask in 1..16 {
+                // Every output bit combination is balanced
+                assert_eq!(lat.bias(0, mask), 0);
+            }
+            for mask in 1..64 {
+                assert_eq!(lat.bias(mask, 0), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn walsh_matches_lat() {
+        for i in 1..=8 {
+            let lat = Lat::des(i);
+
+            for output_mask in 0..16 {
+                let spectrum = walsh_spectrum(&SBOX[i - 1], output_mask);
+
+                for (input_mask, &value) in spectrum.iter().enumerate() {
+                    assert_eq!(value, 2 * lat.bias(input_mask as u8, output_mask) as i32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn walsh_of_linear_function() {
+        // f(x) = x1 ^ x3 on 3 bits (Msb first) only correlates with the mask 0b101
+        let truth_table: Vec<bool> = (0..8).map(|x| (x >> 2 ^ x) & 1 == 1).collect();
+        assert_eq!(walsh_hadamard(&truth_table), vec![0, 0, 0, 0, 0, 8, 0, 0]);
+    }
+
+    #[test]
+    fn custom_sbox() {
+        let mut sbox = SBOX[4];
+        assert_eq!(Lat::new(&sbox).unwrap(), Lat::des(5));
+
+        // Rows don't have to be permutations
+        let constant = Lat::new(&[[0; 16]; 4]).unwrap();
+        assert_eq!(constant.bias(0, 0b0101), 32);
+
+        sbox[3][15] = 0xff;
+        assert_eq!(
+            Lat::new(&sbox),
+            Err(SboxEntryError {
+                row: 3,
+                column: 15,
+                value: 0xff
+            })
+        );
+    }
+
+    #[test]
+    fn output() {
+        let csv = Lat::des(5).to_csv();
+        assert_eq!(
+            csv.lines().nth(0x11).unwrap(),
+            "10,0,2,-2,0,0,-2,-6,-8,0,-2,-2,-4,0,2,10,-20"
+        );
+        assert_eq!(Lat::des(5).to_string().lines().count(), 65);
+    }
+}
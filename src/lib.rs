@@ -3,130 +3,278 @@
 #![allow(dead_code)]
 
 mod bits;
+pub mod bitslice;
+pub mod characteristic;
+pub mod cipher;
+pub mod ddt;
+mod des;
+pub mod differential;
+pub mod key;
+pub mod lat;
+pub mod linear;
+pub mod modes;
+pub mod padding;
+pub mod permutation;
+pub mod schedule;
+pub mod sp;
+mod trace;
+pub mod trail;
+pub mod triple_des;
+pub mod variant;
 pub use bits::Bits;
+pub use des::Des;
+use permutation::LutPermutation;
+#[cfg(target_arch = "x86_64")]
 use std::arch::asm;
+use std::ops::RangeInclusive;
+pub use trace::{RoundTrace, Trace};
 
 const ROUNDS: usize = 16;
 
+static IP_LUT: LutPermutation<64, 64> = LutPermutation::new(&IP);
+static IP_INVERSE_LUT: LutPermutation<64, 64> = LutPermutation::new(&IP_INVERSE);
+static PC1_LUT: LutPermutation<64, 56> = LutPermutation::new(&PC1);
+static PC2_LUT: LutPermutation<56, 48> = LutPermutation::new(&PC2);
+static P_LUT: LutPermutation<32, 32> = LutPermutation::new(&P);
+
 pub fn encrypt(plaintext: Bits<64>, key: Bits<64>) -> Bits<64> {
     let keys = generate_keys::<ROUNDS>(key);
-    dbg!(plaintext);
 
     // Apply IP
-    let data = plaintext.permute(&IP);
-    dbg!(data);
+    let data = IP_LUT.apply(plaintext);
+
+    // Apply rounds
+    let data = weak_encrypt(data, &keys);
+
+    // Apply IP_INVERSE
+    IP_INVERSE_LUT.apply(data)
+}
+
+/// Same as `encrypt`, but records every intermediate value along the way.
+/// The returned `Trace` can be printed to get a round by round walkthrough.
+pub fn encrypt_traced(plaintext: Bits<64>, key: Bits<64>) -> Trace {
+    let keys = generate_keys::<ROUNDS>(key);
+
+    // Apply IP
+    let ip = plaintext.permute(&IP);
 
     // split
-    let (mut u, mut v) = data.split::<32>();
+    let (mut u, mut v) = ip.split::<32>();
+    let (l, r) = (u, v);
 
-    println!("L {:#034b}", u.as_u64());
-    println!("R {:#034b}\n", v.as_u64());
+    let mut rounds = Vec::with_capacity(ROUNDS);
 
     // Apply rounds
-    for round in 1..=ROUNDS {
-        println!("Round {round}");
-        // Apply Feistel function
-        let key = keys[round - 1];
-
+    for key in keys {
         // apply E
         let e = v.permute(&E);
-        println!("E {:#050b}", e.as_u64());
-        println!("fake E {:#050b}", e.as_u64());
-
         let keyed = e ^ key;
-        println!("E^K {:#050b}", e.as_u64());
 
         // apply S-box
-        let mut sbox_output: u64 = 0;
-
-        for (i, sbox) in SBOX.iter().enumerate() {
-            let slice: Bits<6> = keyed.range(i * 6 + 1, i * 6 + 6);
-            let p = 2 * slice.get(1) as u64 + slice.get(6) as u64;
-
-            let n = slice.const_range::<2, 5>().as_u64();
-
-            // Sbox i, row p and element n
-            sbox_output <<= 4;
-            sbox_output |= sbox[p as usize][n as usize] as u64;
-        }
-        let sbox_output: Bits<32> = Bits::new(sbox_output);
-        println!("Sbox {:#034b}", sbox_output.as_u64());
+        let sbox_output = sbox_layer(keyed);
 
         // Apply P
         let p = sbox_output.permute(&P);
-        println!("P {:#034b}", p.as_u64());
 
         // xor p onto u
         u = u ^ p;
 
-        println!("L {:#034b}", u.as_u64());
-        println!("R {:#034b}\n", v.as_u64());
+        rounds.push(RoundTrace {
+            e,
+            keyed,
+            sbox: sbox_output,
+            p,
+            l: u,
+            r: v,
+        });
 
         // Swap sides
         (u, v) = (v, u);
     }
 
     // un-swap the last swap amd concatenate them
-    let data = v.concat(u);
+    let preoutput = v.concat(u);
+
+    Trace {
+        plaintext,
+        ip,
+        l,
+        r,
+        rounds,
+        preoutput,
+        ciphertext: preoutput.permute(&IP_INVERSE),
+    }
+}
+
+/// Decrypts a block by running the rounds with the key schedule in reverse order.
+pub fn decrypt(ciphertext: Bits<64>, key: Bits<64>) -> Bits<64> {
+    let keys = generate_keys::<ROUNDS>(key);
+
+    // Apply IP
+    let data = IP_LUT.apply(ciphertext);
+
+    // The Feistel network is its own inverse when the keys are reversed
+    let data = weak_decrypt(data, &keys);
 
     // Apply IP_INVERSE
-    let ciphertext = data.permute(&IP_INVERSE);
-    ciphertext
+    IP_INVERSE_LUT.apply(data)
 }
 
 pub fn weak_encrypt<const R: usize>(plaintext: Bits<64>, keys: &[Bits<48>; R]) -> Bits<64> {
+    apply_rounds(plaintext, keys)
+}
+
+/// Inverse of `weak_encrypt`. Takes the same (forward) keys and applies them in reverse order.
+pub fn weak_decrypt<const R: usize>(ciphertext: Bits<64>, keys: &[Bits<48>; R]) -> Bits<64> {
+    apply_rounds(ciphertext, keys.iter().rev())
+}
+
+/// Runs one round per key, without IP and with the last swap undone.
+fn apply_rounds<'a>(block: Bits<64>, keys: impl IntoIterator<Item = &'a Bits<48>>) -> Bits<64> {
     // split
-    let (mut u, mut v) = plaintext.split::<32>();
+    let (mut u, mut v) = block.split::<32>();
 
     // println!("L {:#034b}", u.as_u64());
     // println!("R {:#034b}\n", v.as_u64());
 
     // Apply rounds
-    for round in 1..=R {
-        // println!("Round {}", round);
-        // Apply Feistel function
-        let key = keys[round - 1];
+    for &key in keys {
+        // xor the Feistel function onto u
+        u = u ^ feistel(v, key);
 
-        // apply E
-        let e = fast_expand(v);
-        // println!("E {:#050b}", e.as_u64());
-        // println!("fake E {:#050b}", e.as_u64());
+        // println!("L {:#034b}", u.as_u64());
+        // println!("R {:#034b}\n", v.as_u64());
 
-        let keyed = e ^ key;
-        // println!("E^K {:#050b}", e.as_u64());
+        // Swap sides
+        (u, v) = (v, u);
+    }
 
-        // apply S-box
-        let mut sbox_output: u64 = 0;
+    // un-swap the last swap amd concatenate them
+    v.concat(u)
+}
 
-        for (i, sbox) in SBOX.iter().enumerate() {
-            let slice: Bits<6> = keyed.range(i * 6 + 1, i * 6 + 6);
-            let p = 2 * slice.get(1) as u64 + slice.get(6) as u64;
+/// The DES round function f(R, K) = P(S(E(R) ^ K))
+pub fn feistel(r: Bits<32>, k: Bits<48>) -> Bits<32> {
+    // apply E
+    let e = fast_expand(r);
+    // println!("E {:#050b}", e.as_u64());
 
-            let n = slice.const_range::<2, 5>().as_u64();
+    let keyed = e ^ k;
+    // println!("E^K {:#050b}", keyed.as_u64());
 
-            // Sbox i, row p and element n
-            sbox_output <<= 4;
-            sbox_output |= sbox[p as usize][n as usize] as u64;
-        }
-        let sbox_output: Bits<32> = Bits::new(sbox_output);
-        // println!("Sbox {:#034b}", sbox_output.as_u64());
+    // apply S-box
+    let sbox_output = sbox_layer(keyed);
+    // println!("Sbox {:#034b}", sbox_output.as_u64());
 
-        // Apply P
-        let p = fast_p_permute(sbox_output);
-        // println!("P {:#034b}", p.as_u64());
+    // Apply P
+    fast_p_permute(sbox_output)
+}
 
-        // xor p onto u
-        u = u ^ p;
+/// Looks up `input` in S-box `i`, where `i` is 1-indexed like S1 to S8.
+pub fn sbox(i: usize, input: Bits<6>) -> Bits<4> {
+    assert!((1..=8).contains(&i));
+    Bits::new(sbox_lookup(&SBOX[i - 1], input.as_u64() as usize) as u64)
+}
 
-        // println!("L {:#034b}", u.as_u64());
-        // println!("R {:#034b}\n", v.as_u64());
+/// Looks up a 6 bit input (as an integer) in a single S-box table. The outer bits of
+/// the input select the row and the inner 4 bits the column.
+pub(crate) fn sbox_lookup(table: &[[u8; 16]; 4], input: usize) -> u8 {
+    table[(input >> 4 & 2) | (input & 1)][(input >> 1) & 0xf]
+}
 
-        // Swap sides
-        (u, v) = (v, u);
+/// Applies all 8 S-boxes to the keyed expansion
+fn sbox_layer(keyed: Bits<48>) -> Bits<32> {
+    let mut sbox_output: u64 = 0;
+
+    for i in 1..=8 {
+        let slice: Bits<6> = keyed.range(i * 6 - 5, i * 6);
+
+        sbox_output <<= 4;
+        sbox_output |= sbox(i, slice).as_u64();
     }
 
-    // un-swap the last swap amd concatenate them
-    v.concat(u)
+    Bits::new(sbox_output)
+}
+
+/// Swaps the left and right half of a block
+fn swap_halves(block: Bits<64>) -> Bits<64> {
+    let (l, r) = block.split::<32>();
+    r.concat(l)
+}
+
+/// Options for `encrypt_rounds` and `decrypt_rounds`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RoundOptions {
+    /// Apply IP before the rounds and IP_INVERSE after them
+    pub initial_permutation: bool,
+    /// Keep the swap after the last round, so the output is L || R and can be fed
+    /// straight into the next round. DES (and `weak_encrypt`) undo it.
+    pub final_swap: bool,
+}
+
+impl RoundOptions {
+    /// What full DES does: IP and no final swap
+    pub const DES: RoundOptions = RoundOptions {
+        initial_permutation: true,
+        final_swap: false,
+    };
+}
+
+/// Runs the rounds in `rounds` (1-indexed and inclusive) only. `keys[round - 1]` is
+/// used as the key of `round`, so a full key schedule can be passed in.
+/// `encrypt_rounds(block, &keys, 1..=R, RoundOptions::default())` is `weak_encrypt`.
+pub fn encrypt_rounds(
+    block: Bits<64>,
+    keys: &[Bits<48>],
+    rounds: RangeInclusive<usize>,
+    options: RoundOptions,
+) -> Bits<64> {
+    assert!(*rounds.start() > 0);
+    assert!(*rounds.end() <= keys.len());
+
+    let mut data = block;
+    if options.initial_permutation {
+        data = IP_LUT.apply(data);
+    }
+
+    data = apply_rounds(data, &keys[rounds.start() - 1..*rounds.end()]);
+
+    if options.final_swap {
+        data = swap_halves(data);
+    }
+    if options.initial_permutation {
+        data = IP_INVERSE_LUT.apply(data);
+    }
+
+    data
+}
+
+/// Inverse of `encrypt_rounds` with the same arguments. This is what peeling off the
+/// last rounds looks like.
+pub fn decrypt_rounds(
+    block: Bits<64>,
+    keys: &[Bits<48>],
+    rounds: RangeInclusive<usize>,
+    options: RoundOptions,
+) -> Bits<64> {
+    assert!(*rounds.start() > 0);
+    assert!(*rounds.end() <= keys.len());
+
+    let mut data = block;
+    if options.initial_permutation {
+        data = IP_LUT.apply(data);
+    }
+    if options.final_swap {
+        data = swap_halves(data);
+    }
+
+    data = apply_rounds(data, keys[rounds.start() - 1..*rounds.end()].iter().rev());
+
+    if options.initial_permutation {
+        data = IP_INVERSE_LUT.apply(data);
+    }
+
+    data
 }
 
 pub fn fast_expand(input: Bits<32>) -> Bits<48> {
@@ -144,6 +292,7 @@ pub fn fast_expand(input: Bits<32>) -> Bits<48> {
     Bits::new(output)
 }
 
+#[cfg(target_arch = "x86_64")]
 #[inline(always)]
 /// 32-bit pext instruction
 unsafe fn pext_u32(a: u32, mask: u32) -> u32 {
@@ -158,20 +307,65 @@ unsafe fn pext_u32(a: u32, mask: u32) -> u32 {
     value
 }
 
-/// https://programming.sirrida.de/calcperm.php
+/// Whether `pext` exists and is fast. It's microcoded and very slow on AMD before Zen 3.
+#[cfg(target_arch = "x86_64")]
+fn has_fast_pext() -> bool {
+    use std::arch::x86_64::__cpuid;
+    use std::sync::OnceLock;
+
+    static FAST_PEXT: OnceLock<bool> = OnceLock::new();
+
+    *FAST_PEXT.get_or_init(|| {
+        if !is_x86_feature_detected!("bmi2") {
+            return false;
+        }
+
+        let vendor = __cpuid(0);
+        let is_amd = (vendor.ebx, vendor.edx, vendor.ecx) == (0x68747541, 0x69746e65, 0x444d4163);
+
+        let signature = __cpuid(1).eax;
+        let mut family = (signature >> 8) & 0xf;
+        if family == 0xf {
+            family += (signature >> 20) & 0xff;
+        }
+
+        // Zen 3 is family 19h
+        !is_amd || family >= 0x19
+    })
+}
+
+/// P permutation, using `pext` when the CPU has a fast one and a lookup table otherwise.
 pub fn fast_p_permute(input: Bits<32>) -> Bits<32> {
-    let mut x = input.as_u64() as u32;
-    unsafe {
-        x = (pext_u32(x, 0xf801371f) << 16) | pext_u32(x, 0x07fec8e0);
-        x = (pext_u32(x, 0xce896751) << 16) | pext_u32(x, 0x317698ae);
-        x = (pext_u32(x, 0xd8cc3a95) << 16) | pext_u32(x, 0x2733c56a);
-        x = (pext_u32(x, 0x6ca635aa) << 16) | pext_u32(x, 0x9359ca55);
-        x = (pext_u32(x, 0x69a59996) << 16) | pext_u32(x, 0x965a6669);
+    #[cfg(target_arch = "x86_64")]
+    if has_fast_pext() {
+        // Safety: we just checked that the CPU supports BMI2
+        return unsafe { pext_p_permute(input) };
     }
 
+    table_p_permute(input)
+}
+
+/// https://programming.sirrida.de/calcperm.php
+///
+/// # Safety
+/// The CPU has to support BMI2.
+#[cfg(target_arch = "x86_64")]
+pub unsafe fn pext_p_permute(input: Bits<32>) -> Bits<32> {
+    let mut x = input.as_u64() as u32;
+    x = (pext_u32(x, 0xf801371f) << 16) | pext_u32(x, 0x07fec8e0);
+    x = (pext_u32(x, 0xce896751) << 16) | pext_u32(x, 0x317698ae);
+    x = (pext_u32(x, 0xd8cc3a95) << 16) | pext_u32(x, 0x2733c56a);
+    x = (pext_u32(x, 0x6ca635aa) << 16) | pext_u32(x, 0x9359ca55);
+    x = (pext_u32(x, 0x69a59996) << 16) | pext_u32(x, 0x965a6669);
+
     Bits::new(x as u64)
 }
 
+/// Portable P permutation using byte-wise lookup tables
+pub fn table_p_permute(input: Bits<32>) -> Bits<32> {
+    P_LUT.apply(input)
+}
+
 fn trim_key(key: Bits<64>) -> Bits<56> {
     let a: Bits<7> = key.const_range::<1, 7>();
     let b: Bits<7> = key.const_range::<9, 15>();
@@ -218,7 +412,7 @@ const PC2: [u8; 48] = [
 const LSHIFT_MAP: [u8; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
 
 pub fn generate_keys<const R: usize>(key: Bits<64>) -> [Bits<48>; R] {
-    let mut cd = key.permute(&PC1);
+    let mut cd = PC1_LUT.apply(key);
     // println!("cd[ 0] = {:#058b}", cd.as_u64());
 
     let mut keys: [Bits<48>; R] = [Bits::new(0); R];
@@ -231,7 +425,7 @@ pub fn generate_keys<const R: usize>(key: Bits<64>) -> [Bits<48>; R] {
         d = d.rotate_left(shift);
         cd = c.concat(d);
 
-        let key = cd.permute(&PC2);
+        let key = PC2_LUT.apply(cd);
         keys[i - 1] = key;
 
         // println!("cd[{:2}] = {:#058b}", i, cd.as_u64());
@@ -382,6 +576,129 @@ mod test {
         assert_eq!(ciphertext.as_u64(), 0x8ca64de9c1b123a7);
     }
 
+    #[test]
+    fn decrypt_test_vector() {
+        let plaintext: Bits<64> = Bits::new(0x4e6f772069732074);
+        let key: Bits<64> = Bits::new(0x0123456789abcdef);
+
+        let ciphertext = encrypt(plaintext, key);
+        assert_eq!(ciphertext.as_u64(), 0x3fa40e8a984d4815);
+        assert_eq!(decrypt(ciphertext, key), plaintext);
+    }
+
+    #[test]
+    fn decrypt_zero_key() {
+        let key: Bits<64> = Bits::new(0);
+        let ciphertext: Bits<64> = Bits::new(0x8ca64de9c1b123a7);
+
+        assert_eq!(decrypt(ciphertext, key).as_u64(), 0);
+        assert_eq!(decrypt(encrypt(ciphertext, key), key), ciphertext);
+    }
+
+    #[test]
+    fn weak_decrypt_round_trip() {
+        let keys = generate_keys::<5>(Bits::new(0xdeadbeefcafebabe));
+        let plaintext: Bits<64> = Bits::new(0x4141414141414141);
+
+        let ciphertext = weak_encrypt(plaintext, &keys);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(weak_decrypt(ciphertext, &keys), plaintext);
+    }
+
+    #[test]
+    fn traced_matches_encrypt() {
+        let plaintext: Bits<64> = Bits::new(0x4e6f772069732074);
+        let key: Bits<64> = Bits::new(0x0123456789abcdef);
+
+        let trace = encrypt_traced(plaintext, key);
+        assert_eq!(trace.rounds.len(), ROUNDS);
+        assert_eq!(trace.ciphertext, encrypt(plaintext, key));
+        assert_eq!(trace.ip, plaintext.permute(&IP));
+        assert_eq!(trace.preoutput, trace.ciphertext.permute(&IP));
+
+        // The first round only touches the left half
+        let first = trace.rounds[0];
+        assert_eq!(first.r, trace.r);
+        assert_eq!(first.l, trace.l ^ first.p);
+    }
+
+    #[test]
+    fn round_ranges() {
+        let plaintext: Bits<64> = Bits::new(0x4e6f772069732074);
+        let key: Bits<64> = Bits::new(0x0123456789abcdef);
+        let keys = generate_keys::<ROUNDS>(key);
+
+        // The full range is DES
+        let ciphertext = encrypt_rounds(plaintext, &keys, 1..=16, RoundOptions::DES);
+        assert_eq!(ciphertext, encrypt(plaintext, key));
+        assert_eq!(
+            decrypt_rounds(ciphertext, &keys, 1..=16, RoundOptions::DES),
+            plaintext
+        );
+
+        let weak: [Bits<48>; 5] = keys[..5].try_into().unwrap();
+        assert_eq!(
+            encrypt_rounds(plaintext, &keys, 1..=5, RoundOptions::default()),
+            weak_encrypt(plaintext, &weak)
+        );
+
+        // Chaining ranges with the swap kept is the same as running them at once
+        let chained = RoundOptions {
+            initial_permutation: false,
+            final_swap: true,
+        };
+        let middle = encrypt_rounds(plaintext, &keys, 1..=7, chained);
+        let full = encrypt_rounds(middle, &keys, 8..=16, RoundOptions::default());
+        assert_eq!(
+            full,
+            encrypt_rounds(plaintext, &keys, 1..=16, Default::default())
+        );
+
+        // Peeling off the last round gives the state after round 15
+        let preoutput = ciphertext.permute(&IP);
+        let fifteen = decrypt_rounds(preoutput, &keys, 16..=16, RoundOptions::default());
+        assert_eq!(
+            fifteen,
+            encrypt_rounds(plaintext.permute(&IP), &keys, 1..=15, chained)
+        );
+
+        for options in [RoundOptions::DES, chained, RoundOptions::default()] {
+            let block = encrypt_rounds(plaintext, &keys, 4..=9, options);
+            assert_eq!(decrypt_rounds(block, &keys, 4..=9, options), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_sbox() {
+        // Example from FIPS 46-3: S1(011011) is row 01, column 1101
+        assert_eq!(sbox(1, Bits::new(0b011011)).as_u64(), 5);
+        assert_eq!(sbox(8, Bits::new(0b111111)).as_u64(), 11);
+
+        // Every row is a permutation of 0..16
+        for i in 1..=8 {
+            for row in 0..4 {
+                let mut seen = [false; 16];
+                for column in 0..16 {
+                    let input = Bits::new((row & 2) << 4 | column << 1 | (row & 1));
+                    seen[sbox(i, input).as_u64() as usize] = true;
+                }
+                assert!(seen.iter().all(|&x| x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_feistel() {
+        let trace = encrypt_traced(Bits::new(0x4e6f772069732074), Bits::new(0x0123456789abcdef));
+        let keys = generate_keys::<ROUNDS>(Bits::new(0x0123456789abcdef));
+
+        let mut r = trace.r;
+        for (round, key) in trace.rounds.iter().zip(keys) {
+            assert_eq!(feistel(r, key), round.p);
+            r = round.l;
+        }
+    }
+
     #[test]
     fn generate_keys_doesnt_panic() {
         let key: Bits<64> = Bits::new(0xFF);
@@ -429,4 +746,22 @@ mod test {
             assert_eq!(output.as_u64(), fast.as_u64())
         }
     }
+
+    #[test]
+    fn p_permute_implementations_agree() {
+        let mut inputs: Vec<Bits<32>> = (0..32).map(|i| Bits::new(1 << i)).collect();
+        inputs.extend((0..1000).map(|_| Bits::new(rand::random::<u32>() as u64)));
+
+        for input in inputs {
+            let expected = input.permute(&P);
+
+            assert_eq!(fast_p_permute(input), expected);
+            assert_eq!(table_p_permute(input), expected);
+
+            #[cfg(target_arch = "x86_64")]
+            if is_x86_feature_detected!("bmi2") {
+                assert_eq!(unsafe { pext_p_permute(input) }, expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,281 @@
+//! Block cipher modes of operation over byte slices.
+//!
+//! ECB and CBC work on whole blocks, so the input has to be padded to a multiple of
+//! 8 bytes first (see `padding`). CFB, OFB and CTR turn DES into a stream cipher and
+//! accept any length.
+use crate::padding::BLOCK_SIZE;
+use crate::{Bits, Des};
+
+fn to_block(bytes: &[u8]) -> Bits<64> {
+    let mut block = [0; BLOCK_SIZE];
+    block[..bytes.len()].copy_from_slice(bytes);
+    Bits::new(u64::from_be_bytes(block))
+}
+
+fn from_block(block: Bits<64>) -> [u8; BLOCK_SIZE] {
+    block.as_u64().to_be_bytes()
+}
+
+fn assert_whole_blocks(data: &[u8]) {
+    assert!(
+        data.len().is_multiple_of(BLOCK_SIZE),
+        "Input length must be a multiple of the block size."
+    );
+}
+
+/// XORs `data` with a keystream that is generated 8 bytes at a time.
+fn apply_keystream(data: &[u8], mut next: impl FnMut() -> Bits<64>) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let keystream = from_block(next());
+        output.extend(chunk.iter().zip(keystream).map(|(a, b)| a ^ b));
+    }
+
+    output
+}
+
+/// Electronic codebook. Panics if the input isn't a multiple of 8 bytes.
+pub fn ecb_encrypt(data: &[u8], key: Bits<64>) -> Vec<u8> {
+    assert_whole_blocks(data);
+    let des = Des::new(key);
+
+    data.chunks(BLOCK_SIZE)
+        .flat_map(|chunk| from_block(des.encrypt_block(to_block(chunk))))
+        .collect()
+}
+
+/// See `ecb_encrypt`
+pub fn ecb_decrypt(data: &[u8], key: Bits<64>) -> Vec<u8> {
+    assert_whole_blocks(data);
+    let des = Des::new(key);
+
+    data.chunks(BLOCK_SIZE)
+        .flat_map(|chunk| from_block(des.decrypt_block(to_block(chunk))))
+        .collect()
+}
+
+/// Cipher block chaining. Panics if the input isn't a multiple of 8 bytes.
+pub fn cbc_encrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    assert_whole_blocks(data);
+    let des = Des::new(key);
+
+    let mut previous = iv;
+    data.chunks(BLOCK_SIZE)
+        .flat_map(|chunk| {
+            previous = des.encrypt_block(to_block(chunk) ^ previous);
+            from_block(previous)
+        })
+        .collect()
+}
+
+/// See `cbc_encrypt`
+pub fn cbc_decrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    assert_whole_blocks(data);
+    let des = Des::new(key);
+
+    let mut previous = iv;
+    data.chunks(BLOCK_SIZE)
+        .flat_map(|chunk| {
+            let block = to_block(chunk);
+            let plaintext = des.decrypt_block(block) ^ previous;
+            previous = block;
+            from_block(plaintext)
+        })
+        .collect()
+}
+
+/// Cipher feedback with 64-bit segments. A trailing partial block is allowed.
+pub fn cfb64_encrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut output = Vec::with_capacity(data.len());
+    let mut register = iv;
+
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let keystream = from_block(des.encrypt_block(register));
+        let ciphertext: Vec<u8> = chunk.iter().zip(keystream).map(|(a, b)| a ^ b).collect();
+
+        register = to_block(&ciphertext);
+        output.extend(ciphertext);
+    }
+
+    output
+}
+
+/// See `cfb64_encrypt`
+pub fn cfb64_decrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut output = Vec::with_capacity(data.len());
+    let mut register = iv;
+
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let keystream = from_block(des.encrypt_block(register));
+        output.extend(chunk.iter().zip(keystream).map(|(a, b)| a ^ b));
+
+        register = to_block(chunk);
+    }
+
+    output
+}
+
+/// Shifts a ciphertext byte into the CFB-8 register
+fn shift_register(register: Bits<64>, byte: u8) -> Bits<64> {
+    Bits::new(register.as_u64() << 8 | byte as u64)
+}
+
+/// Cipher feedback with 8-bit segments.
+pub fn cfb8_encrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut register = iv;
+
+    data.iter()
+        .map(|byte| {
+            let keystream = from_block(des.encrypt_block(register))[0];
+            let ciphertext = byte ^ keystream;
+            register = shift_register(register, ciphertext);
+            ciphertext
+        })
+        .collect()
+}
+
+/// See `cfb8_encrypt`
+pub fn cfb8_decrypt(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut register = iv;
+
+    data.iter()
+        .map(|byte| {
+            let keystream = from_block(des.encrypt_block(register))[0];
+            register = shift_register(register, *byte);
+            byte ^ keystream
+        })
+        .collect()
+}
+
+/// Output feedback with 64-bit segments. Encryption and decryption are the same operation.
+pub fn ofb(data: &[u8], key: Bits<64>, iv: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut register = iv;
+
+    apply_keystream(data, || {
+        register = des.encrypt_block(register);
+        register
+    })
+}
+
+/// Counter mode. The counter starts at `nonce` and is incremented as a big-endian
+/// 64-bit integer, wrapping around. Encryption and decryption are the same operation.
+pub fn ctr(data: &[u8], key: Bits<64>, nonce: Bits<64>) -> Vec<u8> {
+    let des = Des::new(key);
+    let mut counter = nonce.as_u64();
+
+    apply_keystream(data, || {
+        let keystream = des.encrypt_block(Bits::new(counter));
+        counter = counter.wrapping_add(1);
+        keystream
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Test vectors from FIPS 81
+    const KEY: u64 = 0x0123456789abcdef;
+    const IV: u64 = 0x1234567890abcdef;
+    const PLAINTEXT: &[u8] = b"Now is the time for all ";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn ecb() {
+        let expected = hex("3fa40e8a984d48156a271787ab8883f9893d51ec4b563b53");
+
+        let ciphertext = ecb_encrypt(PLAINTEXT, Bits::new(KEY));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(ecb_decrypt(&ciphertext, Bits::new(KEY)), PLAINTEXT);
+    }
+
+    #[test]
+    fn cbc() {
+        let expected = hex("e5c7cdde872bf27c43e934008c389c0f683788499a7c05f6");
+
+        let ciphertext = cbc_encrypt(PLAINTEXT, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(
+            cbc_decrypt(&ciphertext, Bits::new(KEY), Bits::new(IV)),
+            PLAINTEXT
+        );
+    }
+
+    #[test]
+    fn cfb64() {
+        let expected = hex("f3096249c7f46e51a69e839b1a92f78403467133898ea622");
+
+        let ciphertext = cfb64_encrypt(PLAINTEXT, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(
+            cfb64_decrypt(&ciphertext, Bits::new(KEY), Bits::new(IV)),
+            PLAINTEXT
+        );
+
+        // Partial last segment
+        let short = &PLAINTEXT[..19];
+        let ciphertext = cfb64_encrypt(short, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected[..19]);
+        assert_eq!(
+            cfb64_decrypt(&ciphertext, Bits::new(KEY), Bits::new(IV)),
+            short
+        );
+    }
+
+    #[test]
+    fn cfb8() {
+        let expected = hex("f31fda07011462ee187f43d80a7cd9b5b0d290da6e5b9a87");
+
+        let ciphertext = cfb8_encrypt(PLAINTEXT, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(
+            cfb8_decrypt(&ciphertext, Bits::new(KEY), Bits::new(IV)),
+            PLAINTEXT
+        );
+    }
+
+    #[test]
+    fn ofb64() {
+        let expected = hex("f3096249c7f46e5135f24a242eeb3d3f3d6d5be3255af8c3");
+
+        let ciphertext = ofb(PLAINTEXT, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(ofb(&ciphertext, Bits::new(KEY), Bits::new(IV)), PLAINTEXT);
+    }
+
+    #[test]
+    fn ctr64() {
+        // Keystream is ECB of IV, IV + 1 and IV + 2
+        let expected = hex("f3096249c7f46e51163a8ca0ffc94c27fa2f80f480b86f75");
+
+        let ciphertext = ctr(PLAINTEXT, Bits::new(KEY), Bits::new(IV));
+        assert_eq!(ciphertext, expected);
+        assert_eq!(ctr(&ciphertext, Bits::new(KEY), Bits::new(IV)), PLAINTEXT);
+    }
+
+    #[test]
+    fn ctr_wraps_around() {
+        let data = [0; 16];
+        let ciphertext = ctr(&data, Bits::new(KEY), Bits::new(u64::MAX));
+
+        assert_eq!(ciphertext[8..], ecb_encrypt(&[0; 8], Bits::new(KEY)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn ecb_partial_block() {
+        ecb_encrypt(&PLAINTEXT[..10], Bits::new(KEY));
+    }
+}
@@ -0,0 +1,232 @@
+//! Padding schemes for the 8 byte DES block.
+//!
+//! Unpadding never panics on bad input. It reports exactly which check failed, which
+//! is what a padding oracle leaks.
+use std::fmt;
+
+/// Block size of `Bits<64>` in bytes
+pub const BLOCK_SIZE: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Padding {
+    /// Every padding byte is the amount of padding, `.. 03 03 03`
+    Pkcs7,
+    /// A 0x80 marker followed by zeros, `.. 80 00 00`
+    Iso7816,
+    /// Zeros followed by the amount of padding, `.. 00 00 03`
+    AnsiX923,
+    /// Zeros up to the block boundary and nothing if already aligned. Not reversible
+    /// if the message itself ends in zeros.
+    Zero,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaddingError {
+    /// The input is empty or isn't a multiple of the block size
+    InvalidLength(usize),
+    /// The padding length byte is zero or bigger than a block
+    InvalidPadLength(u8),
+    /// A padding byte has the wrong value. `index` is relative to the start of the input.
+    InvalidPadByte { index: usize, value: u8 },
+    /// No 0x80 marker was found in the last block
+    MissingMarker,
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::InvalidLength(len) => {
+                write!(f, "length {len} is not a positive multiple of {BLOCK_SIZE}")
+            }
+            PaddingError::InvalidPadLength(len) => write!(f, "invalid padding length {len}"),
+            PaddingError::InvalidPadByte { index, value } => {
+                write!(f, "invalid padding byte {value:#04x} at index {index}")
+            }
+            PaddingError::MissingMarker => write!(f, "missing 0x80 padding marker"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+impl Padding {
+    /// Pads `data` to a multiple of the block size. Every scheme except `Zero` always
+    /// adds at least one byte, so a full block of padding is added to aligned input.
+    pub fn pad(self, data: &[u8]) -> Vec<u8> {
+        let pad_len = match self {
+            Padding::Zero => (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE,
+            _ => BLOCK_SIZE - data.len() % BLOCK_SIZE,
+        };
+
+        let mut output = Vec::with_capacity(data.len() + pad_len);
+        output.extend_from_slice(data);
+
+        match self {
+            Padding::Pkcs7 => output.resize(data.len() + pad_len, pad_len as u8),
+            Padding::Iso7816 => {
+                output.push(0x80);
+                output.resize(data.len() + pad_len, 0);
+            }
+            Padding::AnsiX923 => {
+                output.resize(data.len() + pad_len - 1, 0);
+                output.push(pad_len as u8);
+            }
+            Padding::Zero => output.resize(data.len() + pad_len, 0),
+        }
+
+        output
+    }
+
+    /// Strips the padding from `data`, checking every padding byte.
+    pub fn unpad(self, data: &[u8]) -> Result<&[u8], PaddingError> {
+        if !data.len().is_multiple_of(BLOCK_SIZE) || (data.is_empty() && self != Padding::Zero) {
+            return Err(PaddingError::InvalidLength(data.len()));
+        }
+
+        match self {
+            Padding::Pkcs7 | Padding::AnsiX923 => {
+                let last = data[data.len() - 1];
+                if last == 0 || last as usize > BLOCK_SIZE {
+                    return Err(PaddingError::InvalidPadLength(last));
+                }
+
+                let start = data.len() - last as usize;
+                let expected = if self == Padding::Pkcs7 { last } else { 0 };
+
+                for (index, &value) in data.iter().enumerate().take(data.len() - 1).skip(start) {
+                    if value != expected {
+                        return Err(PaddingError::InvalidPadByte { index, value });
+                    }
+                }
+
+                Ok(&data[..start])
+            }
+            Padding::Iso7816 => {
+                let block_start = data.len() - BLOCK_SIZE;
+
+                for index in (block_start..data.len()).rev() {
+                    match data[index] {
+                        0x00 => continue,
+                        0x80 => return Ok(&data[..index]),
+                        value => return Err(PaddingError::InvalidPadByte { index, value }),
+                    }
+                }
+
+                Err(PaddingError::MissingMarker)
+            }
+            Padding::Zero => {
+                let block_start = data.len().saturating_sub(BLOCK_SIZE);
+                let mut end = data.len();
+
+                while end > block_start && data[end - 1] == 0 {
+                    end -= 1;
+                }
+
+                Ok(&data[..end])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pkcs7() {
+        assert_eq!(
+            Padding::Pkcs7.pad(b"hello"),
+            [b'h', b'e', b'l', b'l', b'o', 3, 3, 3]
+        );
+        assert_eq!(Padding::Pkcs7.pad(b"12345678")[8..], [8; 8]);
+
+        for len in 0..20 {
+            let data = vec![0xaa; len];
+            let padded = Padding::Pkcs7.pad(&data);
+            assert_eq!(Padding::Pkcs7.unpad(&padded), Ok(&data[..]));
+        }
+    }
+
+    #[test]
+    fn pkcs7_errors() {
+        assert_eq!(
+            Padding::Pkcs7.unpad(b"1234567"),
+            Err(PaddingError::InvalidLength(7))
+        );
+        assert_eq!(
+            Padding::Pkcs7.unpad(b""),
+            Err(PaddingError::InvalidLength(0))
+        );
+        assert_eq!(
+            Padding::Pkcs7.unpad(&[1, 2, 3, 4, 5, 6, 7, 0]),
+            Err(PaddingError::InvalidPadLength(0))
+        );
+        assert_eq!(
+            Padding::Pkcs7.unpad(&[1, 2, 3, 4, 5, 6, 7, 9]),
+            Err(PaddingError::InvalidPadLength(9))
+        );
+        assert_eq!(
+            Padding::Pkcs7.unpad(&[1, 2, 3, 4, 5, 3, 2, 3]),
+            Err(PaddingError::InvalidPadByte { index: 6, value: 2 })
+        );
+    }
+
+    #[test]
+    fn iso7816() {
+        assert_eq!(
+            Padding::Iso7816.pad(b"hello"),
+            [b'h', b'e', b'l', b'l', b'o', 0x80, 0, 0]
+        );
+
+        for len in 0..20 {
+            let data = vec![0x80; len];
+            let padded = Padding::Iso7816.pad(&data);
+            assert_eq!(Padding::Iso7816.unpad(&padded), Ok(&data[..]));
+        }
+
+        assert_eq!(
+            Padding::Iso7816.unpad(&[0; 8]),
+            Err(PaddingError::MissingMarker)
+        );
+        assert_eq!(
+            Padding::Iso7816.unpad(&[1, 2, 3, 4, 5, 0x80, 1, 0]),
+            Err(PaddingError::InvalidPadByte { index: 6, value: 1 })
+        );
+    }
+
+    #[test]
+    fn ansi_x923() {
+        assert_eq!(
+            Padding::AnsiX923.pad(b"hello"),
+            [b'h', b'e', b'l', b'l', b'o', 0, 0, 3]
+        );
+
+        for len in 0..20 {
+            let data = vec![0; len];
+            let padded = Padding::AnsiX923.pad(&data);
+            assert_eq!(Padding::AnsiX923.unpad(&padded), Ok(&data[..]));
+        }
+
+        assert_eq!(
+            Padding::AnsiX923.unpad(&[1, 2, 3, 4, 5, 3, 3, 3]),
+            Err(PaddingError::InvalidPadByte { index: 5, value: 3 })
+        );
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(
+            Padding::Zero.pad(b"hello"),
+            [b'h', b'e', b'l', b'l', b'o', 0, 0, 0]
+        );
+        assert_eq!(Padding::Zero.pad(b"12345678"), b"12345678");
+        assert_eq!(Padding::Zero.pad(b""), b"");
+
+        assert_eq!(Padding::Zero.unpad(b"hello\0\0\0"), Ok(&b"hello"[..]));
+        assert_eq!(Padding::Zero.unpad(b""), Ok(&b""[..]));
+        assert_eq!(
+            Padding::Zero.unpad(b"hello"),
+            Err(PaddingError::InvalidLength(5))
+        );
+    }
+}
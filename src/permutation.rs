@@ -0,0 +1,108 @@
+//! Permutations compiled into lookup tables at compile time.
+//!
+//! `Bits::permute` moves one bit at a time, which is slow. A `LutPermutation` splits
+//! the input into bytes and looks up the permuted output of every byte in a table, then
+//! ORs the results together. The tables are built by a `const fn` from the same
+//! 1-indexed Msb-first tables DES uses, so any table (including ones that repeat bits
+//! like `E`) can be turned into fast code with
+//!
+//! ```
+//! # use morningstar::{permutation::LutPermutation, Bits};
+//! static SWAP: LutPermutation<4, 4> = LutPermutation::new(&[3, 4, 1, 2]);
+//!
+//! assert_eq!(SWAP.apply(Bits::new(0b1101)).as_u64(), 0b0111);
+//! ```
+use crate::Bits;
+
+/// A permutation from `IN` bits to `OUT` bits backed by byte-wise lookup tables.
+/// The tables take 16 KiB, so put these in a `static` instead of a `const`.
+pub struct LutPermutation<const IN: usize, const OUT: usize> {
+    /// `tables[k][v]` is the output when byte `k` (counting from the least significant
+    /// byte) of the input is `v` and every other byte is zero.
+    tables: [[u64; 256]; 8],
+}
+
+impl<const IN: usize, const OUT: usize> LutPermutation<IN, OUT> {
+    /// Compiles a permutation. Panics (at compile time when used in a `static`) if an
+    /// index is out of range.
+    pub const fn new(permutation: &[u8; OUT]) -> Self {
+        assert!(IN <= 64 && OUT <= 64);
+
+        let mut tables = [[0; 256]; 8];
+
+        // Output bit i (1-indexed, Msb first) is input bit permutation[i - 1]
+        let mut i = 0;
+        while i < OUT {
+            let j = permutation[i] as usize;
+            assert!(j > 0 && j <= IN);
+
+            // Position of the input bit counting from the least significant bit
+            let shift = IN - j;
+            let byte = shift / 8;
+
+            let mut value = 0;
+            while value < 256 {
+                if (value >> (shift % 8)) & 1 == 1 {
+                    tables[byte][value] |= 1 << (OUT - 1 - i);
+                }
+                value += 1;
+            }
+
+            i += 1;
+        }
+
+        LutPermutation { tables }
+    }
+
+    /// Same as `input.permute(&permutation)`
+    #[inline]
+    pub fn apply(&self, input: Bits<IN>) -> Bits<OUT> {
+        let input = input.as_u64();
+        let mut output = 0;
+
+        for (k, table) in self.tables.iter().enumerate().take(IN.div_ceil(8)) {
+            output |= table[((input >> (8 * k)) & 0xff) as usize];
+        }
+
+        Bits::new(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{E, IP, IP_INVERSE, P, PC1, PC2};
+
+    fn check<const IN: usize, const OUT: usize>(permutation: &[u8; OUT]) {
+        let lut = LutPermutation::<IN, OUT>::new(permutation);
+
+        let mut inputs: Vec<u64> = (0..IN).map(|i| 1 << i).collect();
+        inputs.extend((0..1000).map(|_| rand::random::<u64>() >> (64 - IN)));
+
+        for input in inputs {
+            let input = Bits::<IN>::new(input);
+            assert_eq!(lut.apply(input), input.permute(permutation));
+        }
+    }
+
+    #[test]
+    fn des_tables() {
+        check::<64, 64>(&IP);
+        check::<64, 64>(&IP_INVERSE);
+        check::<64, 56>(&PC1);
+        check::<56, 48>(&PC2);
+        check::<32, 48>(&E);
+        check::<32, 32>(&P);
+    }
+
+    #[test]
+    fn custom_tables() {
+        // Repeated bits and widths that aren't a multiple of 8
+        check::<4, 7>(&[1, 1, 1, 1, 2, 3, 4]);
+        check::<8, 8>(&[2, 3, 5, 8, 1, 4, 6, 7]);
+        check::<28, 28>(&[
+            2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+            26, 27, 28, 1,
+        ]);
+    }
+}
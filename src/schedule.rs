@@ -0,0 +1,365 @@
+//! Inverting the key schedule. Every round key bit is a bit of the 56 bit master key
+//! (after `PC1`), so recovered round key bits pin down master key bits directly.
+//! `KeyBitMap` tells which master key bit ends up where.
+use crate::key::key_from_cd;
+use crate::{Bits, LSHIFT_MAP, PC1, PC2};
+use std::fmt;
+
+/// Total rotation of the C and D registers after `round` rounds
+fn total_shift(round: usize) -> usize {
+    LSHIFT_MAP[..round].iter().map(|&s| s as usize).sum()
+}
+
+/// Position in the initial C and D register (the output of `PC1`) that bit `bit` of
+/// round key `round` comes from. Both are 1-indexed.
+pub(crate) fn cd_position(round: usize, bit: usize) -> usize {
+    assert!((1..=16).contains(&round));
+    assert!((1..=48).contains(&bit));
+
+    let position = PC2[bit - 1] as usize;
+    let shift = total_shift(round);
+
+    // Rotating left by `shift` moves the bit at `q + shift` to `q`
+    let (offset, q) = if position <= 28 {
+        (0, position)
+    } else {
+        (28, position - 28)
+    };
+
+    offset + (q - 1 + shift) % 28 + 1
+}
+
+/// Table of where every round key bit comes from in the 64 bit master key.
+/// Rounds and bits are 1-indexed, like everywhere else.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyBitMap {
+    /// `forward[round - 1][bit - 1]` is the master key bit
+    forward: [[u8; 48]; 16],
+    /// `reverse[master_bit - 1]` is every (round, bit) that uses the master key bit
+    reverse: Vec<Vec<(usize, usize)>>,
+}
+
+impl KeyBitMap {
+    /// Derives the table from `PC1`, `PC2` and `LSHIFT_MAP`.
+    pub fn new() -> KeyBitMap {
+        let mut forward = [[0; 48]; 16];
+        let mut reverse = vec![Vec::new(); 64];
+
+        for round in 1..=16 {
+            for bit in 1..=48 {
+                let master_bit = PC1[cd_position(round, bit) - 1];
+                forward[round - 1][bit - 1] = master_bit;
+                reverse[master_bit as usize - 1].push((round, bit));
+            }
+        }
+
+        KeyBitMap { forward, reverse }
+    }
+
+    /// Master key bit that ends up as bit `bit` of round key `round`
+    pub fn master_bit(&self, round: usize, bit: usize) -> usize {
+        self.forward[round - 1][bit - 1] as usize
+    }
+
+    /// Every (round, bit) of the round keys that is a copy of `master_bit`.
+    /// Empty for the parity bits.
+    pub fn round_key_bits(&self, master_bit: usize) -> &[(usize, usize)] {
+        &self.reverse[master_bit - 1]
+    }
+
+    /// Round key bits of `round_a` and `round_b` that come from the same master key bit,
+    /// as (master bit, bit in `round_a`, bit in `round_b`).
+    pub fn overlap(&self, round_a: usize, round_b: usize) -> Vec<(usize, usize, usize)> {
+        let mut overlap = Vec::new();
+
+        for bit_a in 1..=48 {
+            let master_bit = self.master_bit(round_a, bit_a);
+
+            for &(round, bit_b) in self.round_key_bits(master_bit) {
+                if round == round_b {
+                    overlap.push((master_bit, bit_a, bit_b));
+                }
+            }
+        }
+
+        overlap
+    }
+}
+
+impl Default for KeyBitMap {
+    fn default() -> Self {
+        KeyBitMap::new()
+    }
+}
+
+/// Known bits of a single round key. Only the bits set in `mask` are used.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PartialRoundKey {
+    /// Round the key belongs to, 1-indexed
+    pub round: usize,
+    pub key: Bits<48>,
+    pub mask: Bits<48>,
+}
+
+impl PartialRoundKey {
+    /// A round key where every bit is known
+    pub fn full(round: usize, key: Bits<48>) -> PartialRoundKey {
+        PartialRoundKey {
+            round,
+            key,
+            mask: Bits::new((1 << 48) - 1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScheduleError {
+    /// Two known round key bits map to the same master key bit but disagree on its value
+    Inconsistent { round: usize, bit: usize },
+    /// A round key for a round outside of 1 to 16
+    Round { round: usize },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Inconsistent { round, bit } => {
+                write!(
+                    f,
+                    "bit {bit} of round key {round} contradicts an earlier round key"
+                )
+            }
+            ScheduleError::Round { round } => {
+                write!(f, "DES has no round {round}, only 1 to 16")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+/// The set of master keys that agree with some known round key bits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyCandidates {
+    /// Known bits of the `PC1` output
+    pub cd: Bits<56>,
+    /// Which bits of `cd` are known
+    pub mask: Bits<56>,
+}
+
+impl KeyCandidates {
+    /// Amount of master key bits that still have to be brute forced
+    pub fn unknown_bits(&self) -> usize {
+        56 - self.mask.as_u64().count_ones() as usize
+    }
+
+    /// Amount of candidate keys
+    pub fn count(&self) -> u64 {
+        1 << self.unknown_bits()
+    }
+
+    /// Every candidate master key, with odd parity bits
+    pub fn iter(&self) -> impl Iterator<Item = Bits<64>> {
+        let known = self.cd.as_u64();
+        let unknown = !self.mask.as_u64() & ((1 << 56) - 1);
+
+        (0..self.count()).map(move |n| key_from_cd(Bits::new(known | deposit(n, unknown))))
+    }
+}
+
+/// Software version of `pdep`. Scatters the low bits of `value` into the set bits of `mask`.
+fn deposit(mut value: u64, mask: u64) -> u64 {
+    let mut output = 0;
+    let mut mask = mask;
+
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if value & 1 == 1 {
+            output |= lowest;
+        }
+        value >>= 1;
+        mask ^= lowest;
+    }
+
+    output
+}
+
+/// Maps known round key bits back through `PC2`, the rotations and `PC1`.
+pub fn invert_key_schedule(round_keys: &[PartialRoundKey]) -> Result<KeyCandidates, ScheduleError> {
+    let mut cd = Bits::<56>::new(0);
+    let mut mask = Bits::<56>::new(0);
+
+    for round_key in round_keys {
+        if !(1..=16).contains(&round_key.round) {
+            return Err(ScheduleError::Round {
+                round: round_key.round,
+            });
+        }
+
+        for bit in 1..=48 {
+            if !round_key.mask.get(bit) {
+                continue;
+            }
+
+            let position = cd_position(round_key.round, bit);
+            let value = round_key.key.get(bit);
+
+            if mask.get(position) && cd.get(position) != value {
+                return Err(ScheduleError::Inconsistent {
+                    round: round_key.round,
+                    bit,
+                });
+            }
+
+            cd.set(position, value);
+            mask.set(position, true);
+        }
+    }
+
+    Ok(KeyCandidates { cd, mask })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{encrypt, generate_keys};
+
+    const KEY: u64 = 0x133457799bbcdff1;
+
+    #[test]
+    fn full_round_key() {
+        let keys = generate_keys::<16>(Bits::new(KEY));
+
+        for round in 1..=16 {
+            let candidates =
+                invert_key_schedule(&[PartialRoundKey::full(round, keys[round - 1])]).unwrap();
+            assert_eq!(candidates.unknown_bits(), 8);
+            assert_eq!(candidates.count(), 256);
+
+            let mut found = false;
+            for candidate in candidates.iter() {
+                assert_eq!(generate_keys::<16>(candidate)[round - 1], keys[round - 1]);
+                found |= candidate.as_u64() == KEY;
+            }
+            assert!(found);
+        }
+    }
+
+    #[test]
+    fn two_round_keys() {
+        let keys = generate_keys::<16>(Bits::new(KEY));
+        let candidates = invert_key_schedule(&[
+            PartialRoundKey::full(1, keys[0]),
+            PartialRoundKey::full(2, keys[1]),
+        ])
+        .unwrap();
+
+        // Two adjacent round keys cover the whole master key
+        assert_eq!(candidates.unknown_bits(), 0);
+        let key = candidates.iter().next().unwrap();
+        assert_eq!(key.as_u64(), KEY);
+
+        let plaintext = Bits::new(0x0123456789abcdef);
+        assert_eq!(encrypt(plaintext, key), encrypt(plaintext, Bits::new(KEY)));
+    }
+
+    #[test]
+    fn partial_round_key() {
+        let keys = generate_keys::<16>(Bits::new(KEY));
+
+        // Only the 6 bits going into S1 are known
+        let mask = Bits::new(0b111111 << 42);
+        let candidates = invert_key_schedule(&[PartialRoundKey {
+            round: 16,
+            key: Bits::new(keys[15].as_u64() & mask.as_u64()),
+            mask,
+        }])
+        .unwrap();
+
+        assert_eq!(candidates.unknown_bits(), 50);
+        assert_eq!(
+            candidates.cd.as_u64() & candidates.mask.as_u64(),
+            Bits::<64>::new(KEY).permute(&crate::PC1).as_u64() & candidates.mask.as_u64()
+        );
+    }
+
+    #[test]
+    fn inconsistent_round_keys() {
+        let keys = generate_keys::<16>(Bits::new(KEY));
+        let wrong = keys[1] ^ Bits::new(1);
+
+        assert_eq!(
+            invert_key_schedule(&[
+                PartialRoundKey::full(1, keys[0]),
+                PartialRoundKey::full(2, wrong),
+            ]),
+            Err(ScheduleError::Inconsistent { round: 2, bit: 48 })
+        );
+    }
+
+    #[test]
+    fn round_out_of_range() {
+        let keys = generate_keys::<16>(Bits::new(KEY));
+
+        for round in [0, 17] {
+            assert_eq!(
+                invert_key_schedule(&[PartialRoundKey::full(round, keys[0])]),
+                Err(ScheduleError::Round { round })
+            );
+        }
+    }
+
+    #[test]
+    fn key_bit_map_matches_generate_keys() {
+        let map = KeyBitMap::new();
+        let keys = generate_keys::<16>(Bits::new(KEY));
+
+        for master_bit in 1..=64 {
+            let mut flipped = Bits::<64>::new(KEY);
+            flipped.set(master_bit, !flipped.get(master_bit));
+            let flipped_keys = generate_keys::<16>(flipped);
+
+            let mut expected = Vec::new();
+            for round in 1..=16 {
+                let diff = keys[round - 1] ^ flipped_keys[round - 1];
+                for bit in 1..=48 {
+                    if diff.get(bit) {
+                        expected.push((round, bit));
+                        assert_eq!(map.master_bit(round, bit), master_bit);
+                    }
+                }
+            }
+
+            assert_eq!(map.round_key_bits(master_bit), expected);
+
+            // Parity bits are never used
+            assert_eq!(expected.is_empty(), master_bit % 8 == 0);
+        }
+    }
+
+    #[test]
+    fn key_bit_overlap() {
+        let map = KeyBitMap::new();
+
+        let same = map.overlap(3, 3);
+        assert_eq!(same.len(), 48);
+        assert!(same.iter().all(|(_, a, b)| a == b));
+
+        for (a, b) in [(1, 2), (1, 16), (5, 12)] {
+            let overlap = map.overlap(a, b);
+            assert_eq!(overlap.len(), map.overlap(b, a).len());
+
+            for (master_bit, bit_a, bit_b) in overlap {
+                assert_eq!(map.master_bit(a, bit_a), master_bit);
+                assert_eq!(map.master_bit(b, bit_b), master_bit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deposit() {
+        assert_eq!(deposit(0b101, 0b1101_0000), 0b1001_0000);
+        assert_eq!(deposit(0b111, 0b1101_0000), 0b1101_0000);
+        assert_eq!(deposit(0, 0b1101_0000), 0);
+    }
+}
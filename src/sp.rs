@@ -0,0 +1,128 @@
+//! Table driven DES core. The S-boxes and P are fused into 8 tables of 64 words, so a
+//! round is 8 lookups and some shifts. This mirrors the functions at the crate root and
+//! gives the same results.
+use crate::{generate_keys, Bits, IP_INVERSE_LUT, IP_LUT, P, ROUNDS, SBOX};
+
+/// `SP[i][x]` is P applied to the output of S-box i + 1 for the 6 bit input x,
+/// with every other S-box output set to zero.
+static SP: [[u32; 64]; 8] = {
+    let mut table = [[0; 64]; 8];
+
+    let mut i = 0;
+    while i < 8 {
+        let mut x = 0;
+        while x < 64 {
+            // The outer bits select the row and the inner 4 bits the column
+            let row = (x >> 4 & 2) | (x & 1);
+            let column = (x >> 1) & 0xf;
+            let output = (SBOX[i][row][column] as u32) << (28 - 4 * i);
+
+            // Output bit j (1-indexed, Msb first) is input bit P[j - 1]
+            let mut j = 0;
+            while j < 32 {
+                if (output >> (32 - P[j] as usize)) & 1 == 1 {
+                    table[i][x] |= 1 << (31 - j);
+                }
+                j += 1;
+            }
+
+            x += 1;
+        }
+        i += 1;
+    }
+
+    table
+};
+
+/// The 6 bit chunk of E(r) that goes into S-box `i + 1`. E takes bits 4i to 4i + 5 of r
+/// rotated right by one (0-indexed, Msb first), so rotating the word brings them into
+/// the low bits without building the whole 48 bit expansion.
+#[inline(always)]
+fn expanded_chunk(rotated: u32, i: usize) -> usize {
+    (rotated.rotate_left(4 * i as u32 + 6) & 0x3f) as usize
+}
+
+/// Same as `crate::feistel`
+pub fn feistel(r: Bits<32>, k: Bits<48>) -> Bits<32> {
+    let rotated = (r.as_u64() as u32).rotate_right(1);
+    let k = k.as_u64();
+
+    let mut output = 0;
+    for (i, table) in SP.iter().enumerate() {
+        let key_chunk = (k >> (42 - 6 * i)) as usize & 0x3f;
+        output |= table[expanded_chunk(rotated, i) ^ key_chunk];
+    }
+
+    Bits::new(output as u64)
+}
+
+fn apply_rounds<'a>(block: Bits<64>, keys: impl IntoIterator<Item = &'a Bits<48>>) -> Bits<64> {
+    let (mut u, mut v) = block.split::<32>();
+
+    for &key in keys {
+        u = u ^ feistel(v, key);
+        (u, v) = (v, u);
+    }
+
+    v.concat(u)
+}
+
+/// Same as `crate::weak_encrypt`
+pub fn weak_encrypt<const R: usize>(plaintext: Bits<64>, keys: &[Bits<48>; R]) -> Bits<64> {
+    apply_rounds(plaintext, keys)
+}
+
+/// Same as `crate::weak_decrypt`
+pub fn weak_decrypt<const R: usize>(ciphertext: Bits<64>, keys: &[Bits<48>; R]) -> Bits<64> {
+    apply_rounds(ciphertext, keys.iter().rev())
+}
+
+/// Same as `crate::encrypt`
+pub fn encrypt(plaintext: Bits<64>, key: Bits<64>) -> Bits<64> {
+    let keys = generate_keys::<ROUNDS>(key);
+    IP_INVERSE_LUT.apply(weak_encrypt(IP_LUT.apply(plaintext), &keys))
+}
+
+/// Same as `crate::decrypt`
+pub fn decrypt(ciphertext: Bits<64>, key: Bits<64>) -> Bits<64> {
+    let keys = generate_keys::<ROUNDS>(key);
+    IP_INVERSE_LUT.apply(weak_decrypt(IP_LUT.apply(ciphertext), &keys))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::random;
+
+    #[test]
+    fn test_vector() {
+        let plaintext: Bits<64> = Bits::new(0x4e6f772069732074);
+        let key: Bits<64> = Bits::new(0x0123456789abcdef);
+
+        let ciphertext = encrypt(plaintext, key);
+        assert_eq!(ciphertext.as_u64(), 0x3fa40e8a984d4815);
+        assert_eq!(decrypt(ciphertext, key), plaintext);
+    }
+
+    #[test]
+    fn matches_reference_feistel() {
+        for _ in 0..10_000 {
+            let r = Bits::new(random::<u32>() as u64);
+            let k = Bits::new(random::<u64>() >> 16);
+
+            assert_eq!(feistel(r, k), crate::feistel(r, k));
+        }
+    }
+
+    #[test]
+    fn matches_reference_rounds() {
+        for _ in 0..1000 {
+            let keys = generate_keys::<7>(Bits::new(random()));
+            let plaintext = Bits::new(random());
+
+            let ciphertext = weak_encrypt(plaintext, &keys);
+            assert_eq!(ciphertext, crate::weak_encrypt(plaintext, &keys));
+            assert_eq!(weak_decrypt(ciphertext, &keys), plaintext);
+        }
+    }
+}
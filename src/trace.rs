@@ -0,0 +1,64 @@
+use crate::Bits;
+use std::fmt;
+
+/// Intermediate values of a single DES round, as recorded by `encrypt_traced`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RoundTrace {
+    /// Output of the expansion E
+    pub e: Bits<48>,
+    /// E output xored with the round key
+    pub keyed: Bits<48>,
+    /// Output of the 8 S-boxes
+    pub sbox: Bits<32>,
+    /// Output of the P permutation
+    pub p: Bits<32>,
+    /// Left half after this round, before the swap
+    pub l: Bits<32>,
+    /// Right half after this round, before the swap
+    pub r: Bits<32>,
+}
+
+/// Every intermediate value of a full DES encryption.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trace {
+    pub plaintext: Bits<64>,
+    /// Output of IP
+    pub ip: Bits<64>,
+    /// Left half of the IP output
+    pub l: Bits<32>,
+    /// Right half of the IP output
+    pub r: Bits<32>,
+    pub rounds: Vec<RoundTrace>,
+    /// Output of the last round with the last swap undone, before IP_INVERSE
+    pub preoutput: Bits<64>,
+    pub ciphertext: Bits<64>,
+}
+
+impl fmt::Display for RoundTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "E {:#050b}", self.e.as_u64())?;
+        writeln!(f, "E^K {:#050b}", self.keyed.as_u64())?;
+        writeln!(f, "Sbox {:#034b}", self.sbox.as_u64())?;
+        writeln!(f, "P {:#034b}", self.p.as_u64())?;
+        writeln!(f, "L {:#034b}", self.l.as_u64())?;
+        writeln!(f, "R {:#034b}", self.r.as_u64())
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "plaintext {:#066b}", self.plaintext.as_u64())?;
+        writeln!(f, "IP {:#066b}", self.ip.as_u64())?;
+
+        writeln!(f, "L {:#034b}", self.l.as_u64())?;
+        writeln!(f, "R {:#034b}\n", self.r.as_u64())?;
+
+        for (i, round) in self.rounds.iter().enumerate() {
+            writeln!(f, "Round {}", i + 1)?;
+            writeln!(f, "{round}")?;
+        }
+
+        writeln!(f, "preoutput {:#066b}", self.preoutput.as_u64())?;
+        write!(f, "ciphertext {:#066b}", self.ciphertext.as_u64())
+    }
+}
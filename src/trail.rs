@@ -0,0 +1,450 @@
+//! Search for the best linear approximations (trails) of reduced-round DES.
+//!
+//! This is Matsui's branch-and-bound search from "On correlation between the order of
+//! S-boxes and the strength of DES" over the linear approximation tables. Write a_i for
+//! the mask on the input of the round function in round i and b_i for the mask on its
+//! output. Then b_(i+1) = b_(i-1) ^ a_i, so only the output masks of the first two
+//! rounds are free and every later one is fixed by the rounds before it.
+//!
+//! Inside the search every S-box approximation is measured by its correlation
+//! 2 * (p - 1/2), so that the correlation of a trail is simply the product over its
+//! S-boxes. The bias of the trail, as given by the piling-up lemma, is half of that.
+//!
+//! Masks live in the domain of `weak_encrypt`, so `input` masks L0||R0 after IP and
+//! `output` masks R_r||L_r, in the format `jupiter` passes to
+//! `estimate_linear_probability`.
+use crate::lat::Lat;
+use crate::permutation::LutPermutation;
+use crate::{fast_p_permute, Bits, P};
+use std::fmt;
+
+/// The inverse of P, which moves a mask on the output of P to the S-box outputs
+const P_INVERSE: [u8; 32] = {
+    let mut inverse = [0; 32];
+
+    let mut i = 0;
+    while i < 32 {
+        inverse[P[i] as usize - 1] = i as u8 + 1;
+        i += 1;
+    }
+
+    inverse
+};
+
+static P_INVERSE_LUT: LutPermutation<32, 32> = LutPermutation::new(&P_INVERSE);
+
+/// The mask on the round function input for the S-box input masks `gammas` (6 bits per
+/// S-box, S1 first). E copies some bits twice, so their masks cancel out.
+fn expansion_transpose(gammas: u64) -> u32 {
+    let mut mask = 0;
+
+    for j in 0..8 {
+        // S-box j + 1 reads bits 4j to 4j + 5 (1-indexed and wrapping around at 32)
+        let gamma = (gammas >> (42 - 6 * j) & 0x3f) as u32;
+        mask ^= gamma.rotate_left((27 + 32 - 4 * j as u32) % 32);
+    }
+
+    mask
+}
+
+/// The S-box output masks (4 bits each, S1 first) for the round function output mask `b`
+pub(crate) fn sbox_output_masks(b: u32) -> [u8; 8] {
+    let masks = P_INVERSE_LUT.apply(Bits::new(b as u64)).as_u64();
+    std::array::from_fn(|j| (masks >> (28 - 4 * j) & 0xf) as u8)
+}
+
+/// One round of a trail
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RoundMask {
+    /// Mask on the input of the round function (the right half)
+    pub input: Bits<32>,
+    /// Mask on the output of the round function (after P)
+    pub output: Bits<32>,
+    /// Bias of the round, p - 1/2, relative to the round key bits like `Trail::bias`
+    pub bias: f64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trail {
+    /// Plaintext mask on L0||R0, the `alpha` of `estimate_linear_probability`
+    pub input: Bits<64>,
+    pub rounds: Vec<RoundMask>,
+    /// Ciphertext mask on R_r||L_r, the `beta` of `estimate_linear_probability`
+    pub output: Bits<64>,
+    /// Bias of the whole trail by the piling-up lemma, p - 1/2, where p is the
+    /// probability that `input · plaintext ^ output · ciphertext` equals the xor of the
+    /// round key bits the trail goes through. For a given key the sign of the bias of
+    /// `input · plaintext ^ output · ciphertext` alone flips when those bits xor to 1.
+    pub bias: f64,
+}
+
+impl Trail {
+    /// Number of known plaintexts Matsui's Algorithm 2 needs for a good success rate
+    /// (his rule of thumb of c / bias^2 with c = 8).
+    pub fn plaintexts_needed(&self) -> f64 {
+        8.0 / (self.bias * self.bias)
+    }
+}
+
+impl fmt::Display for Trail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "input  {:016x}", self.input.as_u64())?;
+
+        for (i, round) in self.rounds.iter().enumerate() {
+            writeln!(
+                f,
+                "round {:2}  {:08x} -> {:08x}  bias = {:.4}",
+                i + 1,
+                round.input.as_u64(),
+                round.output.as_u64(),
+                round.bias
+            )?;
+        }
+
+        writeln!(f, "output {:016x}", self.output.as_u64())?;
+        writeln!(f, "bias = {} = 2^{:.2}", self.bias, self.bias.abs().log2())
+    }
+}
+
+struct Search<'a> {
+    rounds: usize,
+    k: usize,
+    /// `approximations[j][d]` are the input masks of S-box j + 1 that correlate with the
+    /// output mask d, with their correlation, strongest first.
+    approximations: &'a [Vec<Vec<(u8, f64)>>],
+    /// `bounds[n]` is the absolute correlation of the best n round trail
+    bounds: Vec<f64>,
+    threshold: f64,
+    /// Best trails so far, strongest first
+    found: Vec<Trail>,
+    /// (a_i, b_i, correlation) of the rounds on the current branch
+    path: Vec<(u32, u32, f64)>,
+}
+
+impl Search<'_> {
+    fn worth_exploring(&self, bound: f64) -> bool {
+        bound >= self.threshold
+            && (self.found.len() < self.k || bound > 2.0 * self.found[self.k - 1].bias.abs())
+    }
+
+    /// Round `i` (1-indexed) after the rounds before it reached correlation `c`
+    fn round(&mut self, i: usize, c: f64) {
+        if i > self.rounds {
+            self.leaf();
+        } else if i <= 2 {
+            self.free_sbox(i, 0, 0, 0, c, 1.0);
+        } else {
+            let b = self.path[i - 2].0 ^ self.path[i - 3].1;
+            self.fixed_sbox(i, b, &sbox_output_masks(b), 0, 0, c, 1.0);
+        }
+    }
+
+    /// Strongest correlation the S-boxes from `j` on can reach with the output masks `deltas`
+    fn best_approximations(&self, deltas: &[u8; 8], j: usize) -> f64 {
+        (j..8)
+            .map(|m| self.approximations[m][deltas[m] as usize][0].1.abs())
+            .product()
+    }
+
+    /// Picks the output and input mask of S-box `j` (0-indexed) in one of the two free
+    /// rounds. `deltas` and `gammas` hold the masks chosen so far and `q` is the
+    /// correlation of the round so far.
+    fn free_sbox(&mut self, i: usize, j: usize, deltas: u32, gammas: u64, c: f64, q: f64) {
+        if j == 8 {
+            let b = fast_p_permute(Bits::new(deltas as u64)).as_u64() as u32;
+            self.end_round(expansion_transpose(gammas), b, c, q);
+            return;
+        }
+
+        let bound = c * q.abs() * self.bounds[self.rounds - i];
+
+        for delta in 0..16 {
+            // The input mask of round 1 only changes the plaintext mask and none of the
+            // later rounds, so only the best one is searched here and `leaf` fills in
+            // the others.
+            let approximations = &self.approximations[j][delta as usize];
+            let approximations = if i == 1 {
+                &approximations[..1]
+            } else {
+                approximations
+            };
+
+            for &(gamma, t) in approximations {
+                if !self.worth_exploring(bound * t.abs()) {
+                    break;
+                }
+                let deltas = deltas << 4 | delta;
+                let gammas = gammas << 6 | gamma as u64;
+                self.free_sbox(i, j + 1, deltas, gammas, c, q * t);
+            }
+        }
+    }
+
+    /// Picks the input mask of S-box `j` (0-indexed) in a round with a fixed output mask
+    #[allow(clippy::too_many_arguments)]
+    fn fixed_sbox(
+        &mut self,
+        i: usize,
+        b: u32,
+        deltas: &[u8; 8],
+        j: usize,
+        gammas: u64,
+        c: f64,
+        q: f64,
+    ) {
+        if j == 8 {
+            self.end_round(expansion_transpose(gammas), b, c, q);
+            return;
+        }
+
+        // Every remaining S-box takes at best its strongest approximation
+        let bound =
+            c * q.abs() * self.bounds[self.rounds - i] * self.best_approximations(deltas, j + 1);
+
+        for &(gamma, t) in &self.approximations[j][deltas[j] as usize] {
+            if !self.worth_exploring(bound * t.abs()) {
+                break;
+            }
+            let gammas = gammas << 6 | gamma as u64;
+            self.fixed_sbox(i, b, deltas, j + 1, gammas, c, q * t);
+        }
+    }
+
+    fn end_round(&mut self, a: u32, b: u32, c: f64, q: f64) {
+        // Skip the trivial trail with empty masks
+        let trivial = match self.path.len() {
+            0 => self.rounds == 1 && b == 0,
+            1 => b == 0 && self.path[0].1 == 0,
+            _ => false,
+        };
+        if trivial {
+            return;
+        }
+
+        self.path.push((a, b, q));
+        self.round(self.path.len() + 1, c * q.abs());
+        self.path.pop();
+    }
+
+    /// Goes through the input masks of round 1 for a complete branch
+    fn leaf(&mut self) {
+        let rest: f64 = self.path[1..].iter().map(|r| r.2).product();
+        let deltas = sbox_output_masks(self.path[0].1);
+        self.first_round_sbox(&deltas, 0, 0, 1.0, rest);
+    }
+
+    fn first_round_sbox(&mut self, deltas: &[u8; 8], j: usize, gammas: u64, q: f64, rest: f64) {
+        if j == 8 {
+            self.record(expansion_transpose(gammas), q, rest);
+            return;
+        }
+
+        let bound = rest.abs() * q.abs() * self.best_approximations(deltas, j + 1);
+
+        for &(gamma, t) in &self.approximations[j][deltas[j] as usize] {
+            if !self.worth_exploring(bound * t.abs()) {
+                break;
+            }
+            self.first_round_sbox(deltas, j + 1, gammas << 6 | gamma as u64, q * t, rest);
+        }
+    }
+
+    /// Adds the branch with `a1` and `q1` as input mask and correlation of round 1
+    fn record(&mut self, a1: u32, q1: f64, rest: f64) {
+        let a = |i: usize| if i == 1 { a1 } else { self.path[i - 1].0 };
+        let b = |i: usize| self.path[i - 1].1;
+        let r = self.rounds;
+
+        let (input, output) = if r == 1 {
+            // L1 is R0, which is already covered by the plaintext mask
+            ((b(1), a(1)), (b(1), 0))
+        } else {
+            ((b(1), a(1) ^ b(2)), (b(r), a(r) ^ b(r - 1)))
+        };
+
+        let mut rounds: Vec<RoundMask> = self
+            .path
+            .iter()
+            .map(|&(a, b, correlation)| RoundMask {
+                input: Bits::new(a as u64),
+                output: Bits::new(b as u64),
+                bias: correlation / 2.0,
+            })
+            .collect();
+        rounds[0].input = Bits::new(a1 as u64);
+        rounds[0].bias = q1 / 2.0;
+
+        let bias = q1 * rest / 2.0;
+        let trail = Trail {
+            input: Bits::<32>::new(input.0 as u64).concat(Bits::<32>::new(input.1 as u64)),
+            rounds,
+            output: Bits::<32>::new(output.0 as u64).concat(Bits::<32>::new(output.1 as u64)),
+            bias,
+        };
+
+        let position = self
+            .found
+            .iter()
+            .position(|t| t.bias.abs() < bias.abs())
+            .unwrap_or(self.found.len());
+        self.found.insert(position, trail);
+        self.found.truncate(self.k);
+    }
+}
+
+fn search_with(rounds: usize, k: usize, bounds: &[f64]) -> Vec<Trail> {
+    let approximations: Vec<Vec<Vec<(u8, f64)>>> = (1..=8)
+        .map(|i| {
+            let lat = Lat::des(i);
+            (0..16)
+                .map(|delta| {
+                    let mut column: Vec<(u8, f64)> = (0..64)
+                        .filter(|&gamma| lat.bias(gamma, delta) != 0)
+                        .map(|gamma| (gamma, lat.bias(gamma, delta) as f64 / 32.0))
+                        .collect();
+                    column.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+                    column
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut search = Search {
+        rounds,
+        k,
+        approximations: &approximations,
+        bounds: bounds.to_vec(),
+        threshold: 1.0,
+        found: Vec::new(),
+        path: Vec::with_capacity(rounds),
+    };
+
+    // Start with a threshold close to the best trail and lower it until there are k
+    // trails above it
+    search.threshold = bounds[rounds - 1] / 4.0;
+    loop {
+        search.round(1, 1.0);
+
+        if search.found.len() == k || search.threshold < f64::MIN_POSITIVE {
+            return search.found;
+        }
+        search.found.clear();
+        search.threshold /= 2.0;
+    }
+}
+
+/// The absolute correlation of the best trail for 0 up to `rounds - 1` rounds. A single
+/// round at the end of a longer trail can be inactive, so it is bounded by 1.
+fn bounds(rounds: usize) -> Vec<f64> {
+    let mut bounds = vec![1.0, 1.0];
+
+    for n in 2..rounds {
+        let best = search_with(n, 1, &bounds);
+        bounds.push(2.0 * best[0].bias.abs());
+    }
+
+    bounds
+}
+
+/// The `k` trails with the largest absolute bias over `rounds` rounds, strongest first.
+/// Trails with the same bias come out in no particular order.
+pub fn search(rounds: usize, k: usize) -> Vec<Trail> {
+    assert!((1..=16).contains(&rounds));
+    assert!(k > 0);
+
+    search_with(rounds, k, &bounds(rounds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate_keys, weak_encrypt, E};
+    use rand::random;
+
+    #[test]
+    fn mask_transformations() {
+        for _ in 0..1000 {
+            let r: Bits<32> = Bits::new(random::<u32>() as u64);
+            let gammas: Bits<48> = Bits::new(random::<u64>() >> 16);
+            let a = Bits::new(expansion_transpose(gammas.as_u64()) as u64);
+            assert_eq!(gammas.dot_product(r.permute(&E)), a.dot_product(r));
+
+            let s: Bits<32> = Bits::new(random::<u32>() as u64);
+            let b: u32 = random();
+            let deltas = sbox_output_masks(b)
+                .iter()
+                .fold(0, |deltas, &d| deltas << 4 | d as u64);
+            assert_eq!(
+                Bits::<32>::new(b as u64).dot_product(s.permute(&P)),
+                Bits::<32>::new(deltas).dot_product(s)
+            );
+        }
+    }
+
+    // Matsui's best linear approximations of reduced-round DES
+    #[test]
+    fn best_biases() {
+        // The best single round is the S5 approximation on its own
+        assert_eq!(search(1, 1)[0].bias, -20.0 / 64.0);
+
+        let expected = [
+            1.25 / 4.0,
+            1.56 / 8.0,
+            1.95 / 32.0,
+            1.22 / 64.0,
+            1.95 / 512.0,
+            1.95 / 1024.0,
+            1.22 / 2048.0,
+        ];
+
+        // bounds[n] is twice the best bias over n rounds
+        let bounds = bounds(9);
+        for (rounds, expected) in (2..).zip(expected) {
+            let bias = bounds[rounds] / 2.0;
+            assert!((bias / expected - 1.0).abs() < 0.01, "{rounds} rounds");
+        }
+    }
+
+    #[test]
+    #[ignore = "takes a while without optimizations"]
+    fn best_biases_full_des() {
+        let bounds = bounds(17);
+
+        for (rounds, expected) in [(14, 1.19 * 2f64.powi(-21)), (16, 1.49 * 2f64.powi(-24))] {
+            let bias = bounds[rounds] / 2.0;
+            assert!((bias / expected - 1.0).abs() < 0.01, "{rounds} rounds");
+        }
+    }
+
+    #[test]
+    fn matsui_three_round_approximation() {
+        // The approximation from jupiter, built from S5 in round 1 and 3
+        let best = &search(3, 1)[0];
+
+        assert_eq!(best.input.as_u64(), 0x21040080_00008000);
+        assert_eq!(best.output.as_u64(), 0x21040080_00008000);
+        assert_eq!(best.bias, 2.0 * (20.0 / 64.0) * (20.0 / 64.0));
+    }
+
+    #[test]
+    fn trail_holds_empirically() {
+        let found = search(4, 5);
+        assert_eq!(found.len(), 5);
+        assert!(found.windows(2).all(|w| w[0].bias.abs() >= w[1].bias.abs()));
+
+        let best = &found[0];
+        let keys = generate_keys::<4>(Bits::new(random()));
+        let total = 200_000;
+        let ones = (0..total)
+            .filter(|_| {
+                let plaintext = Bits::new(random());
+                let ciphertext = weak_encrypt(plaintext, &keys);
+                best.input.dot_product(plaintext) ^ best.output.dot_product(ciphertext)
+            })
+            .count();
+
+        // The sign depends on the key, the size doesn't
+        let bias = (ones as f64 / total as f64 - 0.5).abs();
+        assert!((bias - best.bias.abs()).abs() < 0.01, "{bias}");
+    }
+}
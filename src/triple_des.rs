@@ -0,0 +1,198 @@
+//! Triple DES in EDE (encrypt-decrypt-encrypt) mode as described in NIST SP 800-67.
+use crate::{
+    generate_keys, weak_decrypt, weak_encrypt, Bits, IP_INVERSE_LUT, IP_LUT, PC1_LUT, ROUNDS,
+};
+use std::fmt;
+
+/// The keying options of SP 800-67
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyingOption {
+    /// K1, K2 and K3 are all different (EDE3)
+    Three,
+    /// K1 and K2 are different and K3 = K1 (EDE2)
+    Two,
+    /// K1 = K2 = K3, which degrades to single DES
+    One,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyingError {
+    /// The keys don't have the independence that was asked for. Contains the
+    /// option the keys actually have.
+    WrongOption {
+        expected: KeyingOption,
+        actual: KeyingOption,
+    },
+    /// Two keys are equal in a way that doesn't match any keying option,
+    /// e.g. K1 = K2 with a different K3.
+    Degenerate,
+}
+
+impl fmt::Display for KeyingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyingError::WrongOption { expected, actual } => {
+                write!(f, "expected keying option {expected:?} but got {actual:?}")
+            }
+            KeyingError::Degenerate => write!(f, "keys are not independent"),
+        }
+    }
+}
+
+impl std::error::Error for KeyingError {}
+
+/// Compares keys as the key schedule sees them, ignoring the parity bits.
+fn same_key(a: Bits<64>, b: Bits<64>) -> bool {
+    PC1_LUT.apply(a) == PC1_LUT.apply(b)
+}
+
+/// Works out which keying option a key bundle uses.
+pub fn keying_option(
+    k1: Bits<64>,
+    k2: Bits<64>,
+    k3: Bits<64>,
+) -> Result<KeyingOption, KeyingError> {
+    match (same_key(k1, k2), same_key(k2, k3), same_key(k1, k3)) {
+        (false, false, false) => Ok(KeyingOption::Three),
+        (false, false, true) => Ok(KeyingOption::Two),
+        (true, true, true) => Ok(KeyingOption::One),
+        _ => Err(KeyingError::Degenerate),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TripleDes {
+    k1: [Bits<48>; ROUNDS],
+    k2: [Bits<48>; ROUNDS],
+    k3: [Bits<48>; ROUNDS],
+}
+
+impl TripleDes {
+    /// Three key EDE. The keys aren't checked, see `new_checked`.
+    pub fn new(k1: Bits<64>, k2: Bits<64>, k3: Bits<64>) -> TripleDes {
+        TripleDes {
+            k1: generate_keys(k1),
+            k2: generate_keys(k2),
+            k3: generate_keys(k3),
+        }
+    }
+
+    /// Two key EDE, where K3 = K1.
+    pub fn new_ede2(k1: Bits<64>, k2: Bits<64>) -> TripleDes {
+        TripleDes::new(k1, k2, k1)
+    }
+
+    /// Three key EDE that rejects the keys unless they follow the `expected` keying option.
+    pub fn new_checked(
+        k1: Bits<64>,
+        k2: Bits<64>,
+        k3: Bits<64>,
+        expected: KeyingOption,
+    ) -> Result<TripleDes, KeyingError> {
+        let actual = keying_option(k1, k2, k3)?;
+
+        if actual != expected {
+            return Err(KeyingError::WrongOption { expected, actual });
+        }
+
+        Ok(TripleDes::new(k1, k2, k3))
+    }
+
+    /// Two key EDE that rejects K1 = K2.
+    pub fn new_ede2_checked(k1: Bits<64>, k2: Bits<64>) -> Result<TripleDes, KeyingError> {
+        TripleDes::new_checked(k1, k2, k1, KeyingOption::Two)
+    }
+
+    pub fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        // IP and IP_INVERSE cancel out between the three DES operations
+        let data = IP_LUT.apply(plaintext);
+        let data = weak_encrypt(data, &self.k1);
+        let data = weak_decrypt(data, &self.k2);
+        let data = weak_encrypt(data, &self.k3);
+        IP_INVERSE_LUT.apply(data)
+    }
+
+    pub fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        let data = IP_LUT.apply(ciphertext);
+        let data = weak_decrypt(data, &self.k3);
+        let data = weak_encrypt(data, &self.k2);
+        let data = weak_decrypt(data, &self.k1);
+        IP_INVERSE_LUT.apply(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decrypt, encrypt};
+
+    const K1: u64 = 0x0123456789abcdef;
+    const K2: u64 = 0x23456789abcdef01;
+    const K3: u64 = 0x456789abcdef0123;
+
+    // "The qufck brown fox jump"
+    const PLAINTEXT: [u64; 3] = [0x5468652071756663, 0x6b2062726f776e20, 0x666f78206a756d70];
+
+    // Example from SP 800-67 Appendix B
+    #[test]
+    fn ede3_vector() {
+        let expected: [u64; 3] = [0xa826fd8ce53b855f, 0xcce21c8112256fe6, 0x68d5c05dd9b6b900];
+        let cipher = TripleDes::new(Bits::new(K1), Bits::new(K2), Bits::new(K3));
+
+        for (plaintext, ciphertext) in PLAINTEXT.iter().zip(expected) {
+            let block = cipher.encrypt_block(Bits::new(*plaintext));
+            assert_eq!(block.as_u64(), ciphertext);
+            assert_eq!(cipher.decrypt_block(block).as_u64(), *plaintext);
+        }
+    }
+
+    #[test]
+    fn ede2_vector() {
+        let expected: [u64; 3] = [0xc44862f70cf2fbdc, 0x9077d0909fa91b88, 0x4cabd61fc58e0cbb];
+        let cipher = TripleDes::new_ede2(Bits::new(K1), Bits::new(K2));
+
+        for (plaintext, ciphertext) in PLAINTEXT.iter().zip(expected) {
+            let block = cipher.encrypt_block(Bits::new(*plaintext));
+            assert_eq!(block.as_u64(), ciphertext);
+            assert_eq!(cipher.decrypt_block(block).as_u64(), *plaintext);
+        }
+    }
+
+    #[test]
+    fn keying_option_one_is_single_des() {
+        let key = Bits::new(K1);
+        let cipher = TripleDes::new(key, key, key);
+        let plaintext = Bits::new(PLAINTEXT[0]);
+
+        assert_eq!(cipher.encrypt_block(plaintext), encrypt(plaintext, key));
+        assert_eq!(cipher.decrypt_block(plaintext), decrypt(plaintext, key));
+    }
+
+    #[test]
+    fn keying_validation() {
+        let (k1, k2, k3) = (Bits::new(K1), Bits::new(K2), Bits::new(K3));
+
+        assert!(TripleDes::new_ede2_checked(k1, k2).is_ok());
+        assert!(TripleDes::new_checked(k1, k2, k3, KeyingOption::Three).is_ok());
+
+        assert_eq!(
+            TripleDes::new_ede2_checked(k1, k1),
+            Err(KeyingError::WrongOption {
+                expected: KeyingOption::Two,
+                actual: KeyingOption::One
+            })
+        );
+        assert_eq!(
+            TripleDes::new_checked(k1, k2, k1, KeyingOption::Three),
+            Err(KeyingError::WrongOption {
+                expected: KeyingOption::Three,
+                actual: KeyingOption::Two
+            })
+        );
+        assert_eq!(keying_option(k1, k1, k3), Err(KeyingError::Degenerate));
+
+        // Keys that only differ in the parity bits are the same key
+        let k1_flipped = Bits::new(K1 ^ 0x0101010101010101);
+        assert_eq!(keying_option(k1, k1_flipped, k1), Ok(KeyingOption::One));
+    }
+}
@@ -0,0 +1,415 @@
+//! DES with its tables swapped out, for studying variants like DES with random S-boxes
+//! or an altered key schedule.
+//!
+//! A `DesVariant` starts from `DesVariant::DES` and replaces tables with the `with_*`
+//! methods, which check that the new table keeps DES invertible and well defined. The
+//! functions mirror the ones at the crate root but use the permutations bit by bit, so
+//! they are slower than the fixed DES code.
+use crate::cipher::BlockCipher;
+use crate::{sbox_lookup, Bits, E, IP, IP_INVERSE, LSHIFT_MAP, P, PC1, PC2, ROUNDS, SBOX};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VariantError {
+    /// A row of an S-box isn't a permutation of 0 to 15. Both indices are 0-indexed.
+    SboxRow { sbox: usize, row: usize },
+    /// A table entry points outside of the input
+    OutOfRange {
+        table: &'static str,
+        index: usize,
+        value: u8,
+    },
+    /// A table that has to be a permutation (or an injection) uses an input bit twice
+    Repeated { table: &'static str, value: u8 },
+    /// An input bit of `E` never makes it into the output
+    Unused { table: &'static str, bit: u8 },
+    /// A key schedule rotation of 28 bits or more
+    Shift { round: usize, value: u8 },
+}
+
+impl fmt::Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariantError::SboxRow { sbox, row } => {
+                write!(f, "row {row} of S-box {} is not a permutation", sbox + 1)
+            }
+            VariantError::OutOfRange {
+                table,
+                index,
+                value,
+            } => write!(f, "{table}[{index}] = {value} is out of range"),
+            VariantError::Repeated { table, value } => {
+                write!(f, "{table} uses input bit {value} more than once")
+            }
+            VariantError::Unused { table, bit } => write!(f, "{table} never uses input bit {bit}"),
+            VariantError::Shift { round, value } => {
+                write!(f, "shift {value} in round {round} is out of range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VariantError {}
+
+/// Checks that every entry of a 1-indexed table lies in `1..=input`. With `injective`
+/// no input bit may be used twice, otherwise every input bit has to be used.
+fn check_table(
+    table: &'static str,
+    entries: &[u8],
+    input: usize,
+    injective: bool,
+) -> Result<(), VariantError> {
+    let mut used = [false; 64];
+
+    for (index, &value) in entries.iter().enumerate() {
+        if value == 0 || value as usize > input {
+            return Err(VariantError::OutOfRange {
+                table,
+                index,
+                value,
+            });
+        }
+
+        if injective && used[value as usize - 1] {
+            return Err(VariantError::Repeated { table, value });
+        }
+        used[value as usize - 1] = true;
+    }
+
+    if !injective {
+        if let Some(bit) = used[..input].iter().position(|&used| !used) {
+            return Err(VariantError::Unused {
+                table,
+                bit: bit as u8 + 1,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The tables that make up a DES-like cipher
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DesVariant {
+    sbox: [[[u8; 16]; 4]; 8],
+    p: [u8; 32],
+    e: [u8; 48],
+    pc1: [u8; 56],
+    pc2: [u8; 48],
+    lshift_map: [u8; ROUNDS],
+}
+
+impl Default for DesVariant {
+    fn default() -> Self {
+        DesVariant::DES
+    }
+}
+
+impl DesVariant {
+    /// The standard DES tables
+    pub const DES: DesVariant = DesVariant {
+        sbox: SBOX,
+        p: P,
+        e: E,
+        pc1: PC1,
+        pc2: PC2,
+        lshift_map: LSHIFT_MAP,
+    };
+
+    /// Replaces the S-boxes. Every row has to be a permutation of 0 to 15, like in DES.
+    pub fn with_sboxes(self, sbox: [[[u8; 16]; 4]; 8]) -> Result<DesVariant, VariantError> {
+        for (i, rows) in sbox.iter().enumerate() {
+            for (row, entries) in rows.iter().enumerate() {
+                let mut seen = 0u16;
+                for &value in entries {
+                    if value < 16 {
+                        seen |= 1 << value;
+                    }
+                }
+
+                if seen != u16::MAX {
+                    return Err(VariantError::SboxRow { sbox: i, row });
+                }
+            }
+        }
+
+        Ok(DesVariant { sbox, ..self })
+    }
+
+    /// Replaces P, which has to be a permutation of the 32 S-box output bits.
+    pub fn with_p(self, p: [u8; 32]) -> Result<DesVariant, VariantError> {
+        check_table("P", &p, 32, true)?;
+        Ok(DesVariant { p, ..self })
+    }
+
+    /// Replaces E, which has to use every bit of the right half at least once.
+    pub fn with_e(self, e: [u8; 48]) -> Result<DesVariant, VariantError> {
+        check_table("E", &e, 32, false)?;
+        Ok(DesVariant { e, ..self })
+    }
+
+    /// Replaces the key schedule. PC1 picks 56 distinct bits of the key, PC2 picks 48
+    /// distinct bits of C||D and `lshift_map` holds the rotation of C and D per round.
+    pub fn with_key_schedule(
+        self,
+        pc1: [u8; 56],
+        pc2: [u8; 48],
+        lshift_map: [u8; ROUNDS],
+    ) -> Result<DesVariant, VariantError> {
+        check_table("PC1", &pc1, 64, true)?;
+        check_table("PC2", &pc2, 56, true)?;
+
+        if let Some(round) = lshift_map.iter().position(|&shift| shift >= 28) {
+            return Err(VariantError::Shift {
+                round: round + 1,
+                value: lshift_map[round],
+            });
+        }
+
+        Ok(DesVariant {
+            pc1,
+            pc2,
+            lshift_map,
+            ..self
+        })
+    }
+
+    pub fn sboxes(&self) -> &[[[u8; 16]; 4]; 8] {
+        &self.sbox
+    }
+
+    pub fn p(&self) -> &[u8; 32] {
+        &self.p
+    }
+
+    pub fn e(&self) -> &[u8; 48] {
+        &self.e
+    }
+
+    /// Same as `crate::sbox`
+    pub fn sbox(&self, i: usize, input: Bits<6>) -> Bits<4> {
+        assert!((1..=8).contains(&i));
+        Bits::new(sbox_lookup(&self.sbox[i - 1], input.as_u64() as usize) as u64)
+    }
+
+    /// Same as `crate::feistel`
+    pub fn feistel(&self, r: Bits<32>, k: Bits<48>) -> Bits<32> {
+        let keyed = r.permute(&self.e) ^ k;
+
+        let mut sbox_output: u64 = 0;
+        for i in 1..=8 {
+            sbox_output <<= 4;
+            sbox_output |= self.sbox(i, keyed.range(i * 6 - 5, i * 6)).as_u64();
+        }
+
+        Bits::<32>::new(sbox_output).permute(&self.p)
+    }
+
+    /// Same as `crate::generate_keys`
+    pub fn generate_keys<const R: usize>(&self, key: Bits<64>) -> [Bits<48>; R] {
+        assert!(R <= ROUNDS, "DES has only {ROUNDS} round keys.");
+
+        let mut cd = key.permute(&self.pc1);
+        let mut keys = [Bits::new(0); R];
+
+        for (key, &shift) in keys.iter_mut().zip(&self.lshift_map) {
+            let (c, d) = cd.split::<28>();
+            cd = c
+                .rotate_left(shift as usize)
+                .concat(d.rotate_left(shift as usize));
+
+            *key = cd.permute(&self.pc2);
+        }
+
+        keys
+    }
+
+    fn apply_rounds<'a>(
+        &self,
+        block: Bits<64>,
+        keys: impl IntoIterator<Item = &'a Bits<48>>,
+    ) -> Bits<64> {
+        let (mut u, mut v) = block.split::<32>();
+
+        for &key in keys {
+            u = u ^ self.feistel(v, key);
+            (u, v) = (v, u);
+        }
+
+        v.concat(u)
+    }
+
+    /// Same as `crate::weak_encrypt`
+    pub fn weak_encrypt<const R: usize>(
+        &self,
+        plaintext: Bits<64>,
+        keys: &[Bits<48>; R],
+    ) -> Bits<64> {
+        self.apply_rounds(plaintext, keys)
+    }
+
+    /// Same as `crate::weak_decrypt`
+    pub fn weak_decrypt<const R: usize>(
+        &self,
+        ciphertext: Bits<64>,
+        keys: &[Bits<48>; R],
+    ) -> Bits<64> {
+        self.apply_rounds(ciphertext, keys.iter().rev())
+    }
+
+    /// Same as `crate::encrypt`
+    pub fn encrypt(&self, plaintext: Bits<64>, key: Bits<64>) -> Bits<64> {
+        let keys = self.generate_keys::<ROUNDS>(key);
+        self.weak_encrypt(plaintext.permute(&IP), &keys)
+            .permute(&IP_INVERSE)
+    }
+
+    /// Same as `crate::decrypt`
+    pub fn decrypt(&self, ciphertext: Bits<64>, key: Bits<64>) -> Bits<64> {
+        let keys = self.generate_keys::<ROUNDS>(key);
+        self.weak_decrypt(ciphertext.permute(&IP), &keys)
+            .permute(&IP_INVERSE)
+    }
+}
+
+/// A variant reduced to `R` rounds with the key schedule already run, like
+/// `cipher::WeakDes`. This lets the analysis code run against a variant.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WeakVariant<const R: usize> {
+    variant: DesVariant,
+    keys: [Bits<48>; R],
+}
+
+impl<const R: usize> WeakVariant<R> {
+    pub fn from_round_keys(variant: DesVariant, keys: [Bits<48>; R]) -> WeakVariant<R> {
+        WeakVariant { variant, keys }
+    }
+
+    pub fn round_keys(&self) -> &[Bits<48>; R] {
+        &self.keys
+    }
+}
+
+impl<const R: usize> BlockCipher<64> for WeakVariant<R> {
+    type Key = (DesVariant, Bits<64>);
+
+    fn new((variant, key): Self::Key) -> WeakVariant<R> {
+        WeakVariant {
+            variant,
+            keys: variant.generate_keys(key),
+        }
+    }
+
+    fn encrypt_block(&self, plaintext: Bits<64>) -> Bits<64> {
+        self.variant.weak_encrypt(plaintext, &self.keys)
+    }
+
+    fn decrypt_block(&self, ciphertext: Bits<64>) -> Bits<64> {
+        self.variant.weak_decrypt(ciphertext, &self.keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::{random, thread_rng};
+
+    #[test]
+    fn standard_preset_is_des() {
+        let des = DesVariant::default();
+
+        for _ in 0..100 {
+            let key = Bits::new(random());
+            let plaintext = Bits::new(random());
+
+            let ciphertext = des.encrypt(plaintext, key);
+            assert_eq!(ciphertext, crate::encrypt(plaintext, key));
+            assert_eq!(des.decrypt(ciphertext, key), plaintext);
+            assert_eq!(
+                des.generate_keys::<ROUNDS>(key),
+                crate::generate_keys::<ROUNDS>(key)
+            );
+        }
+    }
+
+    #[test]
+    fn random_sboxes() {
+        let mut rng = thread_rng();
+        let mut sbox = [[[0; 16]; 4]; 8];
+        for row in sbox.iter_mut().flatten() {
+            *row = std::array::from_fn(|i| i as u8);
+            row.shuffle(&mut rng);
+        }
+
+        let variant = DesVariant::DES.with_sboxes(sbox).unwrap();
+        let key = Bits::new(random());
+        let plaintext = Bits::new(random());
+
+        let ciphertext = variant.encrypt(plaintext, key);
+        assert_ne!(ciphertext, crate::encrypt(plaintext, key));
+        assert_eq!(variant.decrypt(ciphertext, key), plaintext);
+
+        let cipher = WeakVariant::<6>::new((variant, key));
+        assert_eq!(
+            cipher.decrypt_block(cipher.encrypt_block(plaintext)),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let mut sbox = SBOX;
+        sbox[4][2][7] = sbox[4][2][8];
+        assert_eq!(
+            DesVariant::DES.with_sboxes(sbox),
+            Err(VariantError::SboxRow { sbox: 4, row: 2 })
+        );
+
+        let mut p = P;
+        p[3] = p[5];
+        assert_eq!(
+            DesVariant::DES.with_p(p),
+            Err(VariantError::Repeated {
+                table: "P",
+                value: p[5]
+            })
+        );
+
+        // E may repeat bits, but has to use all of them
+        let mut e = E;
+        assert!(DesVariant::DES.with_e(e).is_ok());
+        e[1] = 2;
+        e[47] = 2;
+        assert_eq!(
+            DesVariant::DES.with_e(e),
+            Err(VariantError::Unused { table: "E", bit: 1 })
+        );
+        e[0] = 33;
+        assert_eq!(
+            DesVariant::DES.with_e(e),
+            Err(VariantError::OutOfRange {
+                table: "E",
+                index: 0,
+                value: 33
+            })
+        );
+
+        let mut lshift_map = LSHIFT_MAP;
+        lshift_map[9] = 28;
+        assert_eq!(
+            DesVariant::DES.with_key_schedule(PC1, PC2, lshift_map),
+            Err(VariantError::Shift {
+                round: 10,
+                value: 28
+            })
+        );
+
+        // An altered key schedule without rotations is fine
+        let variant = DesVariant::DES
+            .with_key_schedule(PC1, PC2, [0; ROUNDS])
+            .unwrap();
+        let keys = variant.generate_keys::<ROUNDS>(Bits::new(random()));
+        assert!(keys.iter().all(|&k| k == keys[0]));
+    }
+}